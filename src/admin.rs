@@ -0,0 +1,137 @@
+use crate::bot::Bot;
+use futures::future::TryFutureExt as _;
+use log::error;
+use once_cell::sync::Lazy;
+use std::env;
+use std::future::Future;
+use telegram_types::bot::types::{ChatId, Message, UserId};
+
+/// Role of an administrator.
+///
+/// Roles are ordered, so that a higher role has all permissions of lower ones.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Role {
+    /// Can view status of the bots and manage the blocklist.
+    Operator,
+    /// Can additionally shut down and upgrade the program.
+    Owner,
+}
+
+impl Role {
+    fn from_str(s: &str) -> Option<Self> {
+        Some(match s {
+            "operator" => Role::Operator,
+            "owner" => Role::Owner,
+            _ => return None,
+        })
+    }
+}
+
+/// List of administrators.
+///
+/// It is read from `BOT_ADMINS` in the form of `<user id>:<role>,...`,
+/// e.g. `123:owner,456:operator`. If that isn't set, `BOT_ADMIN_ID` is
+/// taken as the only owner.
+static ADMINS: Lazy<Vec<(UserId, Role)>> = Lazy::new(|| {
+    if let Ok(admins) = env::var("BOT_ADMINS") {
+        return parse_admins(&admins).unwrap_or_else(|e| panic!("invalid BOT_ADMINS: {e}"));
+    }
+    let admin_id = env::var("BOT_ADMIN_ID")
+        .ok()
+        .and_then(|s| str::parse(&s).map(UserId).ok())
+        .expect("either BOT_ADMINS or BOT_ADMIN_ID must be specified");
+    vec![(admin_id, Role::Owner)]
+});
+
+/// Chat which start, stop and error messages are sent to.
+///
+/// It is read from `BOT_NOTIFY_CHAT_ID`, and defaults to the first owner.
+static NOTIFY_CHAT: Lazy<ChatId> = Lazy::new(|| match env::var("BOT_NOTIFY_CHAT_ID") {
+    Ok(s) => s
+        .parse()
+        .map(ChatId)
+        .expect("BOT_NOTIFY_CHAT_ID must be a valid chat id"),
+    Err(_) => ADMINS
+        .iter()
+        .find(|(_, role)| *role == Role::Owner)
+        .map(|(id, _)| ChatId(id.0))
+        .expect("at least one owner must be specified"),
+});
+
+pub fn init() {
+    Lazy::force(&ADMINS);
+    Lazy::force(&NOTIFY_CHAT);
+}
+
+/// Check whether the message is sent by an administrator with at least the given role.
+pub fn is_sent_by(message: &Message, role: Role) -> bool {
+    message
+        .from
+        .as_ref()
+        .and_then(|from| get_role(from.id))
+        .is_some_and(|r| r >= role)
+}
+
+fn get_role(user: UserId) -> Option<Role> {
+    ADMINS
+        .iter()
+        .find(|(id, _)| *id == user)
+        .map(|(_, role)| *role)
+}
+
+/// Send a message to the notification chat.
+pub fn notify(bot: &Bot, msg: String) -> impl Future<Output = Result<(), ()>> {
+    bot.send_message(*NOTIFY_CHAT, msg)
+        .execute()
+        .map_ok(|_| ())
+        .map_err(|e| error!("failed to send message to admin: {:?}", e))
+}
+
+fn parse_admins(s: &str) -> Result<Vec<(UserId, Role)>, String> {
+    let admins = s
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            let (id, role) = item.split_once(':').unwrap_or((item, "owner"));
+            let id = id
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid user id {id:?}"))?;
+            let role =
+                Role::from_str(role.trim()).ok_or_else(|| format!("invalid role {role:?}"))?;
+            Ok((UserId(id), role))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    if !admins.iter().any(|(_, role)| *role == Role::Owner) {
+        return Err("no owner specified".to_string());
+    }
+    Ok(admins)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_admins() {
+        assert_eq!(
+            parse_admins("123:owner, 456:operator,789"),
+            Ok(vec![
+                (UserId(123), Role::Owner),
+                (UserId(456), Role::Operator),
+                (UserId(789), Role::Owner),
+            ]),
+        );
+        assert!(parse_admins("123:operator").is_err());
+        assert!(parse_admins("123:admin").is_err());
+        assert!(parse_admins("abc:owner").is_err());
+        assert!(parse_admins("").is_err());
+    }
+
+    #[test]
+    fn test_role_order() {
+        assert!(Role::Owner >= Role::Operator);
+        assert!(Role::Operator < Role::Owner);
+    }
+}
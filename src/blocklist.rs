@@ -0,0 +1,73 @@
+use log::error;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io;
+use telegram_types::bot::types::UserId;
+
+const BLOCKLIST_FILE: &str = "blocklist.json";
+
+/// Users whose commands are ignored by the Eval bot.
+static BLOCKLIST: Lazy<RwLock<BTreeSet<UserId>>> = Lazy::new(|| RwLock::new(load()));
+
+pub fn init() {
+    Lazy::force(&BLOCKLIST);
+}
+
+/// Check whether the user is blocked.
+pub fn contains(user: UserId) -> bool {
+    BLOCKLIST.read().contains(&user)
+}
+
+/// Get all blocked users.
+pub fn list() -> Vec<UserId> {
+    BLOCKLIST.read().iter().copied().collect()
+}
+
+/// Block the user, returning whether the user wasn't blocked before.
+pub fn block(user: UserId) -> bool {
+    let mut guard = BLOCKLIST.write();
+    let inserted = guard.insert(user);
+    if inserted {
+        save(&guard);
+    }
+    inserted
+}
+
+/// Unblock the user, returning whether the user was blocked before.
+pub fn unblock(user: UserId) -> bool {
+    let mut guard = BLOCKLIST.write();
+    let removed = guard.remove(&user);
+    if removed {
+        save(&guard);
+    }
+    removed
+}
+
+fn save(blocklist: &BTreeSet<UserId>) {
+    match File::create(BLOCKLIST_FILE) {
+        Ok(file) => {
+            if let Err(e) = serde_json::to_writer(file, blocklist) {
+                error!("failed to serialize blocklist: {:?}", e);
+            }
+        }
+        Err(e) => error!("failed to create blocklist file: {:?}", e),
+    }
+}
+
+fn load() -> BTreeSet<UserId> {
+    match File::open(BLOCKLIST_FILE) {
+        Ok(file) => match serde_json::from_reader(file) {
+            Ok(blocklist) => return blocklist,
+            Err(e) => error!("failed to parse blocklist file: {:?}", e),
+        },
+        Err(e) => {
+            // It's fine that the file doesn't exist.
+            if e.kind() != io::ErrorKind::NotFound {
+                error!("failed to read blocklist file: {:?}", e);
+            }
+        }
+    }
+    BTreeSet::new()
+}
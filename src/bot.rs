@@ -2,6 +2,8 @@ use derive_more::From;
 use futures::future::TryFutureExt as _;
 use futures::stream::{self, Stream};
 use log::debug;
+use once_cell::sync::Lazy;
+use regex::Regex;
 use reqwest::{Client, Request};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
@@ -13,16 +15,19 @@ use std::marker::PhantomData;
 use std::time::Duration;
 use telegram_types::bot::inline_mode::{AnswerInlineQuery, InlineQueryId, InlineQueryResult};
 use telegram_types::bot::methods::{
-    ApiError, ChatTarget, DeleteMessage, EditMessageText, GetMe, GetUpdates, Method, SendMessage,
-    TelegramResult,
+    ApiError, ChatTarget, DeleteMessage, EditMessageReplyMarkup, EditMessageText, GetChatMember,
+    GetMe, GetUpdates, Method, SendMessage, TelegramResult,
+};
+use telegram_types::bot::types::{
+    ChatId, ChatMember, InlineKeyboardMarkup, Message, MessageId, ParseMode, Update, UpdateId,
+    UserId,
 };
-use telegram_types::bot::types::{ChatId, Message, MessageId, ParseMode, Update, UpdateId};
 use tokio::time::timeout;
 
 const TELEGRAM_TIMEOUT_SECS: u16 = 30;
 
 /// Telegram bot
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct Bot {
     client: Client,
     token: &'static str,
@@ -124,6 +129,47 @@ impl Bot {
         self.build_request(&edit_message)
     }
 
+    pub fn edit_message_with_markup<'a>(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+        text: impl Into<Cow<'a, str>>,
+        markup: InlineKeyboardMarkup,
+    ) -> BotRequest<Message> {
+        let edit_message = EditMessageText::new(ChatTarget::id(chat_id.0), message_id, text)
+            .parse_mode(ParseMode::HTML)
+            .disable_preview()
+            .reply_markup(markup);
+        self.build_request(&edit_message)
+    }
+
+    pub fn edit_reply_markup(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+        markup: InlineKeyboardMarkup,
+    ) -> BotRequest<Message> {
+        let edit_markup = EditReplyMarkup(EditMessageReplyMarkup {
+            chat_id: Some(ChatTarget::id(chat_id.0)),
+            message_id: Some(message_id),
+            inline_message_id: None,
+            reply_markup: Some(markup),
+        });
+        self.build_request(&edit_markup)
+    }
+
+    pub fn answer_callback_query<'a>(
+        &self,
+        callback_query_id: &'a str,
+        text: Option<&'a str>,
+    ) -> BotRequest<bool> {
+        let answer = AnswerCallbackQuery {
+            callback_query_id,
+            text,
+        };
+        self.build_request(&answer)
+    }
+
     pub fn delete_message(&self, chat_id: ChatId, message_id: MessageId) -> BotRequest<bool> {
         let delete_message = DeleteMessage {
             chat_id: ChatTarget::id(chat_id.0),
@@ -132,15 +178,24 @@ impl Bot {
         self.build_request(&delete_message)
     }
 
+    pub fn get_chat_member(&self, chat_id: ChatId, user_id: UserId) -> BotRequest<ChatMember> {
+        let get_chat_member = GetChatMember {
+            chat_id: ChatTarget::id(chat_id.0),
+            user_id,
+        };
+        self.build_request(&get_chat_member)
+    }
+
     pub fn answer_inline_query(
         &self,
         inline_query_id: InlineQueryId,
         results: &[InlineQueryResult<'_>],
+        cache_time: Option<i32>,
     ) -> BotRequest<bool> {
         let answer = AnswerInlineQuery {
             inline_query_id,
             results: results.into(),
-            cache_time: None,
+            cache_time,
             is_personal: None,
             next_offset: None,
             switch_pm_text: None,
@@ -162,6 +217,38 @@ impl Bot {
     }
 }
 
+impl fmt::Debug for Bot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Token is deliberately left out.
+        f.debug_struct("Bot")
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+// Methods missing from telegram_types.
+
+#[derive(Serialize)]
+#[serde(transparent)]
+struct EditReplyMarkup<'a>(EditMessageReplyMarkup<'a>);
+
+impl Method for EditReplyMarkup<'_> {
+    const NAME: &'static str = "editMessageReplyMarkup";
+    type Item = Message;
+}
+
+#[derive(Serialize)]
+struct AnswerCallbackQuery<'a> {
+    callback_query_id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<&'a str>,
+}
+
+impl Method for AnswerCallbackQuery<'_> {
+    const NAME: &'static str = "answerCallbackQuery";
+    type Item = bool;
+}
+
 pub struct BotRequest<T> {
     client: Client,
     request: Result<Request, reqwest::Error>,
@@ -187,13 +274,37 @@ where
     }
 }
 
-#[derive(Debug, From)]
+#[derive(From)]
 pub enum Error {
     Request(reqwest::Error),
     Api(ApiError),
     Parse(ParseError),
 }
 
+// Both `Debug` and `Display` of `Error` go through `redact_token`, because
+// `reqwest::Error` embeds the request URL, which contains the bot token.
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            Error::Request(e) => format!("Request({e:?})"),
+            Error::Api(e) => format!("Api({e:?})"),
+            Error::Parse(e) => format!("Parse({e:?})"),
+        };
+        f.write_str(&redact_token(&text))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            Error::Request(e) => format!("request error: {e}"),
+            Error::Api(e) => format!("api error: {e}"),
+            Error::Parse(e) => format!("parse error: {}", e.error),
+        };
+        f.write_str(&redact_token(&text))
+    }
+}
+
 pub struct ParseError {
     pub data: Vec<u8>,
     pub error: serde_json::Error,
@@ -205,6 +316,53 @@ impl fmt::Debug for ParseError {
     }
 }
 
+impl Error {
+    /// Coarse classification of the error, used for grouping reports.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Request(e) if e.is_timeout() => ErrorKind::Timeout,
+            Error::Request(e) if e.is_connect() => ErrorKind::Connect,
+            Error::Request(e) => match e.status() {
+                Some(status) => ErrorKind::Status(status.as_u16()),
+                None => ErrorKind::Request,
+            },
+            Error::Api(e) => ErrorKind::Api(e.error_code),
+            Error::Parse(_) => ErrorKind::Parse,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ErrorKind {
+    Timeout,
+    Connect,
+    Status(u16),
+    Request,
+    Api(i32),
+    Parse,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::Timeout => write!(f, "timeout"),
+            ErrorKind::Connect => write!(f, "connection error"),
+            ErrorKind::Status(code) => write!(f, "HTTP {code} error"),
+            ErrorKind::Request => write!(f, "request error"),
+            ErrorKind::Api(code) => write!(f, "API {code} error"),
+            ErrorKind::Parse => write!(f, "parse error"),
+        }
+    }
+}
+
+/// Mask anything looking like a Telegram bot token in the given text.
+pub fn redact_token(text: &str) -> Cow<'_, str> {
+    // Token is in the form of `<bot id>:<secret>`, and it appears in API URLs
+    // as `https://api.telegram.org/bot<token>/<method>`.
+    static RE_TOKEN: Lazy<Regex> = Lazy::new(|| Regex::new(r"\d+:[\w-]{30,}").unwrap());
+    RE_TOKEN.replace_all(text, "<redacted>")
+}
+
 fn may_recover_from_error(error: &Error) -> Option<UpdateId> {
     // XXX We should be able to simplify this function once if-let-chain
     // gets stable. See RFC 2497.
@@ -225,3 +383,73 @@ fn may_recover_from_error(error: &Error) -> Option<UpdateId> {
     let id = item.get("update_id")?.as_i64()?;
     Some(UpdateId(id))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use tokio::runtime::Runtime;
+
+    const TOKEN: &str = "123456789:AAHdqTcvCH1vGWJxfSeofSAs0K5PALDsaw0";
+
+    fn assert_redacted(error: &Error) {
+        for text in [format!("{error:?}"), format!("{error}")] {
+            assert!(!text.contains(TOKEN), "token leaked: {text}");
+            assert!(!text.contains(&TOKEN[10..]), "secret leaked: {text}");
+        }
+    }
+
+    fn request_error(client: Client, url: String) -> reqwest::Error {
+        let runtime = Runtime::new().unwrap();
+        runtime
+            .block_on(async { client.post(url).send().await })
+            .expect_err("request should fail")
+    }
+
+    #[test]
+    fn test_redact_token() {
+        let url = format!("https://api.telegram.org/bot{TOKEN}/getUpdates");
+        assert_eq!(
+            redact_token(&url),
+            "https://api.telegram.org/bot<redacted>/getUpdates"
+        );
+        assert_eq!(redact_token("localhost:8080"), "localhost:8080");
+    }
+
+    #[test]
+    fn test_redact_request_error() {
+        // Nothing should be listening on port 1.
+        let url = format!("http://127.0.0.1:1/bot{TOKEN}/getMe");
+        let error = Error::from(request_error(Client::new(), url));
+        assert!(matches!(&error, Error::Request(e) if e.is_connect()));
+        assert!(format!("{error:?}").contains("bot<redacted>/getMe"));
+        assert_redacted(&error);
+    }
+
+    #[test]
+    fn test_redact_timeout_error() {
+        // The listener is never accepted, so the request can only time out.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let url = format!("http://{addr}/bot{TOKEN}/getUpdates");
+        let client = Client::builder()
+            .timeout(Duration::from_millis(100))
+            .build()
+            .unwrap();
+        let error = Error::from(request_error(client, url));
+        assert!(matches!(&error, Error::Request(e) if e.is_timeout()));
+        assert_redacted(&error);
+    }
+
+    #[test]
+    fn test_redact_parse_error() {
+        let data = format!(r#"{{"url": "https://api.telegram.org/bot{TOKEN}/getMe""#);
+        let error = serde_json::from_str::<JsonValue>(&data).unwrap_err();
+        let error = Error::Parse(ParseError {
+            data: data.clone().into_bytes(),
+            error,
+        });
+        assert_redacted(&error);
+        assert!(!redact_token(&data).contains(TOKEN));
+    }
+}
@@ -1,25 +1,43 @@
+use crate::admin::{self, Role};
+use crate::blocklist;
 use crate::bot::{Bot, Error};
+use crate::error_report::ErrorReporter;
+use crate::maintenance;
 use crate::shutdown::Shutdown;
 use crate::task_tracker::TaskSpawner;
+use crate::upgrade;
 use crate::utils;
 use futures::channel::oneshot::{channel, Receiver};
 use futures::future;
 use futures::pin_mut;
 use futures::stream::{Stream, StreamExt as _};
+use htmlescape::encode_minimal;
 use log::{debug, error, info, warn};
 use reqwest::Client;
 use std::env::{self, VarError};
 use std::future::Future;
 use std::sync::Arc;
 use std::time::Duration;
-use telegram_types::bot::types::{Update, UpdateContent, UpdateId};
+use telegram_types::bot::types::{Update, UpdateContent, UpdateId, UserId};
+use tokio::task;
 use tokio::time::sleep;
 
+/// Status of a bot after starting.
+#[derive(Debug)]
+pub enum BotStatus {
+    /// The bot is not configured.
+    Disabled,
+    /// The bot failed to start for the given reason.
+    Failed(String),
+    /// The bot is running.
+    Running(Bot),
+}
+
 pub struct BotRunner<'a> {
     pub client: &'a Client,
     pub spawner: &'a Arc<TaskSpawner>,
     pub shutdown: &'a Arc<Shutdown>,
-    pub report_error: fn(&Bot, &Error),
+    pub reporter: &'a Arc<ErrorReporter>,
 }
 
 impl<'a> BotRunner<'a> {
@@ -29,10 +47,10 @@ impl<'a> BotRunner<'a> {
         token_env: &'static str,
         create_impl: Creator,
         handle_update: Handler,
-    ) -> Receiver<Result<Option<Bot>, ()>>
+    ) -> Receiver<BotStatus>
     where
         Impl: Send + Sync + 'static,
-        Creator: (FnOnce(Bot) -> Impl) + Send + 'static,
+        Creator: (FnOnce(Bot) -> Result<Impl, String>) + Send + 'static,
         Handler: (Fn(Arc<Impl>, UpdateId, UpdateContent) -> HandleResult) + Send + Sync + 'static,
         HandleResult: Future<Output = ()> + Send + 'static,
     {
@@ -41,36 +59,51 @@ impl<'a> BotRunner<'a> {
             Ok(token) => Box::leak(token.into_boxed_str()),
             Err(VarError::NotPresent) => {
                 info!("{} wouldn't start because {} is not set", name, token_env);
-                sender.send(Ok(None)).unwrap();
+                sender.send(BotStatus::Disabled).unwrap();
                 return receiver;
             }
             Err(VarError::NotUnicode(s)) => {
-                panic!("invalid value for {token_env}: {s:?}");
+                error!("invalid value for {}: {:?}", token_env, s);
+                let reason = format!("invalid value for {token_env}");
+                sender.send(BotStatus::Failed(reason)).unwrap();
+                return receiver;
             }
         };
         let client = self.client.clone();
         let spawner = self.spawner.clone();
         let shutdown = self.shutdown.clone();
-        let report_error = self.report_error;
+        let reporter = self.reporter.clone();
         self.spawner.spawn(async move {
-            let bot = match Bot::create(client, token).await {
-                Ok(bot) => bot,
+            let result = match Bot::create(client, token).await {
+                // Creating the implementation may read and parse files,
+                // so it's done outside of the runtime workers.
+                Ok(bot) => {
+                    let bot_clone = bot.clone();
+                    task::spawn_blocking(move || create_impl(bot_clone))
+                        .await
+                        .unwrap_or_else(|e| Err(format!("failed to create bot: {e}")))
+                        .map(|bot_impl| (bot, bot_impl))
+                }
+                Err(e) => Err(e.to_string()),
+            };
+            let (bot, bot_impl) = match result {
+                Ok(result) => result,
                 Err(e) => {
-                    error!("failed to init bot for {}: {:?}", name, e);
-                    sender.send(Err(())).unwrap();
+                    error!("failed to init bot for {}: {}", name, e);
+                    sender.send(BotStatus::Failed(e)).unwrap();
                     return;
                 }
             };
-            sender.send(Ok(Some(bot.clone()))).unwrap();
+            sender.send(BotStatus::Running(bot.clone())).unwrap();
             let stop_signal = shutdown.register();
             let bot_runner = run_bot(
                 &bot,
                 bot.get_updates(),
-                Arc::new(create_impl(bot.clone())),
+                Arc::new(bot_impl),
                 handle_update,
                 spawner,
                 shutdown,
-                report_error,
+                reporter,
             );
             pin_mut!(bot_runner);
             future::select(stop_signal, bot_runner).await;
@@ -86,7 +119,7 @@ async fn run_bot<Impl, Handler, HandleResult>(
     handle_update: Handler,
     spawner: Arc<TaskSpawner>,
     shutdown: Arc<Shutdown>,
-    report_error: fn(&Bot, &Error),
+    reporter: Arc<ErrorReporter>,
 ) where
     Handler: Fn(Arc<Impl>, UpdateId, UpdateContent) -> HandleResult,
     HandleResult: Future<Output = ()> + Send + 'static,
@@ -105,13 +138,19 @@ async fn run_bot<Impl, Handler, HandleResult>(
                 if let Some(Update { update_id, content }) = maybe_update {
                     debug!("{}> handling", update_id.0);
                     let content = content.unwrap_or_default();
-                    if !may_handle_common_command(update_id, &content, bot, &spawner, &shutdown) {
+                    if let Some(user) =
+                        sender_of(&content).filter(|&user| blocklist::contains(user))
+                    {
+                        debug!("{}> ignoring blocked user {}", update_id.0, user.0);
+                    } else if !may_handle_common_command(
+                        update_id, &content, bot, &spawner, &shutdown,
+                    ) {
                         spawner.spawn((handle_update)(bot_impl.clone(), update_id, content));
                     }
                 }
             }
             Some(Err(e)) => {
-                (report_error)(bot, &e);
+                reporter.report(bot, &e);
                 warn!(
                     "{}: telegram error ({} retries): {:?}",
                     bot.username, retried, e,
@@ -129,6 +168,19 @@ async fn run_bot<Impl, Handler, HandleResult>(
     }
 }
 
+/// Get the user who sent the update, if any.
+fn sender_of(content: &UpdateContent) -> Option<UserId> {
+    match content {
+        UpdateContent::Message(message) | UpdateContent::EditedMessage(message) => {
+            message.from.as_ref().map(|from| from.id)
+        }
+        UpdateContent::InlineQuery(query) => Some(query.from.id),
+        UpdateContent::ChosenInlineResult(result) => Some(result.from.id),
+        UpdateContent::CallbackQuery(query) => Some(query.from.id),
+        _ => None,
+    }
+}
+
 fn may_handle_common_command(
     update_id: UpdateId,
     content: &UpdateContent,
@@ -160,20 +212,111 @@ fn may_handle_common_command(
             }
         });
     };
-    match command.trim() {
+    let command = command.trim();
+    let (command, args) = command
+        .split_once(char::is_whitespace)
+        .unwrap_or((command, ""));
+    match command {
         "/about" => {
             send_reply(&crate::ABOUT_MESSAGE);
         }
-        "/shutdown" => {
-            let is_admin = message
-                .from
-                .as_ref()
-                .map_or(false, |from| from.id == *crate::ADMIN_ID);
-            if !is_admin {
+        "/status" => {
+            if !admin::is_sent_by(message, Role::Operator) {
                 return false;
             }
-            send_reply("start shutting down...");
-            shutdown.shutdown();
+            let uptime = crate::START_TIME.elapsed().as_secs();
+            send_reply(&format!(
+                "version: {}\nuptime: {}d {}h {}m\nmaintenance: {}",
+                env!("VERSION"),
+                uptime / 86400,
+                uptime / 3600 % 24,
+                uptime / 60 % 60,
+                if maintenance::notice().is_some() {
+                    "on"
+                } else {
+                    "off"
+                },
+            ));
+        }
+        "/maintenance" => {
+            if !admin::is_sent_by(message, Role::Operator) {
+                return false;
+            }
+            let args = args.trim_start();
+            let (switch, notice) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+            match switch {
+                "on" => {
+                    maintenance::start(notice);
+                    send_reply("maintenance mode on");
+                }
+                "off" => {
+                    maintenance::stop();
+                    send_reply("maintenance mode off");
+                }
+                _ => send_reply("usage: <code>/maintenance on [message]</code> or <code>/maintenance off</code>"),
+            }
+        }
+        "/block" | "/unblock" => {
+            if !admin::is_sent_by(message, Role::Operator) {
+                return false;
+            }
+            let user = match args.trim().parse() {
+                Ok(user) => UserId(user),
+                Err(_) => {
+                    send_reply(&format!("usage: <code>{command} &lt;user id&gt;</code>"));
+                    return true;
+                }
+            };
+            let reply = if command == "/block" {
+                match blocklist::block(user) {
+                    true => "user blocked",
+                    false => "user is already blocked",
+                }
+            } else {
+                match blocklist::unblock(user) {
+                    true => "user unblocked",
+                    false => "user is not blocked",
+                }
+            };
+            send_reply(reply);
+        }
+        "/blocklist" => {
+            if !admin::is_sent_by(message, Role::Operator) {
+                return false;
+            }
+            let list = blocklist::list();
+            if list.is_empty() {
+                send_reply("no user is blocked");
+            } else {
+                let list = list
+                    .iter()
+                    .map(|user| user.0.to_string())
+                    .collect::<Vec<_>>();
+                send_reply(&format!("blocked users: {}", list.join(", ")));
+            }
+        }
+        "/shutdown" | "/upgrade" => {
+            if !admin::is_sent_by(message, Role::Owner) {
+                return false;
+            }
+            if !args.trim().is_empty() {
+                send_reply(&format!("usage: <code>{command}</code>"));
+                return true;
+            }
+            if command == "/shutdown" {
+                send_reply("start shutting down...");
+                shutdown.shutdown();
+            } else {
+                let user = message.from.as_ref().unwrap().id;
+                match upgrade::trigger(user) {
+                    Ok(()) => send_reply("start upgrading..."),
+                    Err(e) => {
+                        let reason = encode_minimal(&e.to_string());
+                        send_reply(&format!("failed to upgrade: {reason}"));
+                        return true;
+                    }
+                }
+            }
             let bot = bot.clone();
             spawner.spawn(async move {
                 let result = bot.confirm_update(update_id).await;
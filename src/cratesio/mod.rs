@@ -1,4 +1,5 @@
 use crate::bot::Bot;
+use crate::maintenance;
 use crate::utils::encode_with_code;
 use htmlescape::encode_minimal;
 use itertools::Itertools;
@@ -33,7 +34,10 @@ impl CratesioBot {
             UpdateContent::InlineQuery(query) => query,
             _ => return,
         };
-        let result = if query.query.is_empty() {
+        let notice = maintenance::notice();
+        let result = if let Some(notice) = notice {
+            Ok(vec![maintenance::inline_query_result(&notice)])
+        } else if query.query.is_empty() {
             self.generate_results("https://crates.io/api/v1/summary", |resp: Summary| {
                 resp.most_recently_downloaded
             })
@@ -56,7 +60,7 @@ impl CratesioBot {
         debug!("replying: {:?}", result);
         let result = self
             .bot
-            .answer_inline_query(query.id, &result)
+            .answer_inline_query(query.id, &result, maintenance::inline_cache_time())
             .execute()
             .await;
         if let Err(e) = result {
@@ -0,0 +1,194 @@
+use crate::admin;
+use crate::bot::{self, redact_token, Bot, Error, ErrorKind};
+use crate::shutdown::Shutdown;
+use crate::task_tracker::TaskSpawner;
+use futures::future;
+use futures::pin_mut;
+use futures::FutureExt as _;
+use htmlescape::encode_minimal;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{sleep_until, Instant};
+
+/// Minimum interval between two reports of the same kind from the same bot.
+const REPORT_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Reporter of errors to the admin.
+///
+/// The first error of each kind from a bot is sent immediately. Further errors
+/// of that kind within `REPORT_INTERVAL` are only counted, and a digest of them
+/// is sent when the interval ends, or when the program is shutting down.
+pub struct ErrorReporter {
+    spawner: Arc<TaskSpawner>,
+    shutdown: Arc<Shutdown>,
+    reports: Mutex<HashMap<(&'static str, ErrorKind), Report>>,
+}
+
+impl ErrorReporter {
+    pub fn new(spawner: Arc<TaskSpawner>, shutdown: Arc<Shutdown>) -> Arc<Self> {
+        Arc::new(ErrorReporter {
+            spawner,
+            shutdown,
+            reports: Default::default(),
+        })
+    }
+
+    pub fn report(self: &Arc<Self>, bot: &Bot, error: &Error) {
+        let key = (bot.username, error.kind());
+        let action = self
+            .reports
+            .lock()
+            .entry(key)
+            .or_default()
+            .record(Instant::now());
+        match action {
+            Action::Send => {
+                let message = format_error(error);
+                self.spawner.spawn(admin::notify(bot, message).map(|_| ()));
+            }
+            Action::Schedule(deadline) => {
+                let reporter = self.clone();
+                let bot = bot.clone();
+                let stop_signal = self.shutdown.register();
+                self.spawner.spawn(async move {
+                    let timer = sleep_until(deadline);
+                    pin_mut!(timer);
+                    future::select(timer, stop_signal).await;
+                    reporter.flush(&bot, key.1).await;
+                });
+            }
+            Action::Suppress => {}
+        }
+    }
+
+    async fn flush(&self, bot: &Bot, kind: ErrorKind) {
+        let digest = {
+            let mut reports = self.reports.lock();
+            let report = match reports.get_mut(&(bot.username, kind)) {
+                Some(report) => report,
+                None => return,
+            };
+            if report.suppressed == 0 {
+                return;
+            }
+            let now = Instant::now();
+            let since = report.suppressed_since.unwrap_or(now);
+            let count = report.take_suppressed(now);
+            format_digest(count, kind, bot.username, now - since)
+        };
+        let _ = admin::notify(bot, digest).await;
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+enum Action {
+    /// Send the error right away.
+    Send,
+    /// Count the error, and send a digest at the given time.
+    Schedule(Instant),
+    /// Count the error, a digest has already been scheduled.
+    Suppress,
+}
+
+#[derive(Default)]
+struct Report {
+    last_sent: Option<Instant>,
+    suppressed: usize,
+    suppressed_since: Option<Instant>,
+}
+
+impl Report {
+    fn record(&mut self, now: Instant) -> Action {
+        match self.last_sent {
+            Some(last_sent) if now < last_sent + REPORT_INTERVAL || self.suppressed > 0 => {
+                self.suppressed += 1;
+                if self.suppressed > 1 {
+                    return Action::Suppress;
+                }
+                self.suppressed_since = Some(now);
+                Action::Schedule(last_sent + REPORT_INTERVAL)
+            }
+            _ => {
+                self.last_sent = Some(now);
+                Action::Send
+            }
+        }
+    }
+
+    fn take_suppressed(&mut self, now: Instant) -> usize {
+        self.last_sent = Some(now);
+        self.suppressed_since = None;
+        std::mem::take(&mut self.suppressed)
+    }
+}
+
+fn format_error(error: &Error) -> String {
+    match error {
+        Error::Parse(bot::ParseError { data, error }) => format!(
+            "parse failed: {:?}\n<pre>{}</pre>",
+            encode_minimal(&format!("{error:?}")),
+            encode_minimal(&redact_token(&String::from_utf8_lossy(data))),
+        ),
+        _ => encode_minimal(&format!("{error:?}")),
+    }
+}
+
+fn format_digest(count: usize, kind: ErrorKind, username: &str, elapsed: Duration) -> String {
+    let minutes = elapsed.as_secs().div_ceil(60);
+    format!(
+        "{} {}{} from @{} in last {} min",
+        count,
+        kind,
+        if count == 1 { "" } else { "s" },
+        username,
+        minutes.max(1),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let start = Instant::now();
+        let mut report = Report::default();
+        assert_eq!(report.record(start), Action::Send);
+        let deadline = start + REPORT_INTERVAL;
+        let t1 = start + Duration::from_secs(1);
+        assert_eq!(report.record(t1), Action::Schedule(deadline));
+        assert_eq!(report.record(t1), Action::Suppress);
+        assert_eq!(report.record(deadline), Action::Suppress);
+        assert_eq!(report.take_suppressed(deadline), 3);
+        // Interval restarts from the digest.
+        let t2 = deadline + Duration::from_secs(1);
+        assert_eq!(
+            report.record(t2),
+            Action::Schedule(deadline + REPORT_INTERVAL)
+        );
+        assert_eq!(report.take_suppressed(t2), 1);
+        // Send immediately again once things calm down.
+        let t3 = t2 + REPORT_INTERVAL;
+        assert_eq!(report.record(t3), Action::Send);
+    }
+
+    #[test]
+    fn test_format_digest() {
+        let ten_min = Duration::from_secs(600);
+        assert_eq!(
+            format_digest(23, ErrorKind::Timeout, "rustevalbot", ten_min),
+            "23 timeouts from @rustevalbot in last 10 min",
+        );
+        assert_eq!(
+            format_digest(
+                1,
+                ErrorKind::Status(502),
+                "cratesiobot",
+                Duration::from_secs(1)
+            ),
+            "1 HTTP 502 error from @cratesiobot in last 1 min",
+        );
+    }
+}
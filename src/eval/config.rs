@@ -0,0 +1,231 @@
+use super::parse::{Channel, Edition, Flags, Mode, Output, Task};
+use log::error;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io;
+use telegram_types::bot::types::ChatId;
+
+const CONFIG_FILE: &str = "eval_config.json";
+
+/// Default settings of chats, keyed by the chat id.
+static CONFIGS: Lazy<RwLock<HashMap<i64, ChatConfig>>> = Lazy::new(|| RwLock::new(load()));
+
+/// Default settings of evaluation in a chat.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ChatConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    channel: Option<Channel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    edition: Option<Edition>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mode: Option<Mode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<Output>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_lines: Option<usize>,
+}
+
+impl ChatConfig {
+    /// Get the settings given in the flags.
+    ///
+    /// Returns `None` if the flags contain anything which cannot be a setting.
+    pub fn from_flags(flags: &Flags) -> Option<Self> {
+        if flags.task.is_some()
+            || flags.bare
+            || flags.raw
+            || flags.pretty
+            || flags.display
+            || flags.show_type
+            || flags.niche
+            || flags.all_channels
+            || flags.version
+            || flags.help
+        {
+            return None;
+        }
+        Some(ChatConfig {
+            channel: flags.channel,
+            edition: flags.edition,
+            mode: flags.mode,
+            output: flags.output,
+            max_lines: flags.max_lines,
+        })
+    }
+
+    /// Override the settings with those set in the other config.
+    pub fn merge(&mut self, other: ChatConfig) {
+        self.channel = other.channel.or(self.channel);
+        self.edition = other.edition.or(self.edition);
+        self.mode = other.mode.or(self.mode);
+        self.output = other.output.or(self.output);
+        self.max_lines = other.max_lines.or(self.max_lines);
+    }
+
+    /// Fill the flags not given in the command with the settings.
+    pub fn apply(&self, flags: &mut Flags) {
+        // Tasks only available on nightly aren't affected by the default channel,
+        // while they still reject other channels given in the command.
+        if !flags.task.is_some_and(Task::is_nightly_only) {
+            flags.channel = flags.channel.or(self.channel);
+        }
+        flags.edition = flags.edition.or(self.edition);
+        flags.mode = flags.mode.or(self.mode);
+        flags.output = flags.output.or(self.output);
+        flags.max_lines = flags.max_lines.or(self.max_lines);
+    }
+
+    /// Describe the settings as flags.
+    pub fn describe(&self) -> String {
+        let mut result = String::new();
+        let mut push = |name: &str, value: &dyn std::fmt::Display| {
+            write!(result, " <code>--{name}={value}</code>").unwrap();
+        };
+        if let Some(channel) = self.channel {
+            push("channel", &channel.as_str());
+        }
+        if let Some(edition) = self.edition {
+            push("edition", &edition.as_str());
+        }
+        if let Some(mode) = self.mode {
+            push("mode", &mode.as_str());
+        }
+        if let Some(output) = self.output {
+            push("output", &output.as_str());
+        }
+        if let Some(max_lines) = self.max_lines {
+            push("lines", &max_lines);
+        }
+        match result.strip_prefix(' ') {
+            Some(flags) => flags.to_string(),
+            None => "(default)".to_string(),
+        }
+    }
+}
+
+pub fn init() {
+    Lazy::force(&CONFIGS);
+}
+
+/// Get the settings of the given chat.
+pub fn get(chat: ChatId) -> ChatConfig {
+    CONFIGS.read().get(&chat.0).cloned().unwrap_or_default()
+}
+
+/// Replace the settings of the given chat.
+pub fn set(chat: ChatId, config: ChatConfig) {
+    let mut configs = CONFIGS.write();
+    if config == ChatConfig::default() {
+        configs.remove(&chat.0);
+    } else {
+        configs.insert(chat.0, config);
+    }
+    match File::create(CONFIG_FILE) {
+        Ok(file) => {
+            if let Err(e) = serde_json::to_writer(file, &*configs) {
+                error!("failed to serialize eval config: {:?}", e);
+            }
+        }
+        Err(e) => error!("failed to create eval config file: {:?}", e),
+    }
+}
+
+fn load() -> HashMap<i64, ChatConfig> {
+    match File::open(CONFIG_FILE) {
+        Ok(file) => match serde_json::from_reader(file) {
+            Ok(configs) => return configs,
+            Err(e) => error!("failed to parse eval config file: {:?}", e),
+        },
+        Err(e) => {
+            // It's fine that the file doesn't exist.
+            if e.kind() != io::ErrorKind::NotFound {
+                error!("failed to read eval config file: {:?}", e);
+            }
+        }
+    }
+    HashMap::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_config() {
+        let mut config = ChatConfig::default();
+        assert_eq!(config.describe(), "(default)");
+        config.merge(ChatConfig {
+            channel: Some(Channel::Nightly),
+            mode: Some(Mode::Release),
+            ..ChatConfig::default()
+        });
+        config.merge(ChatConfig {
+            mode: Some(Mode::Debug),
+            max_lines: Some(5),
+            ..ChatConfig::default()
+        });
+        assert_eq!(
+            config.describe(),
+            "<code>--channel=nightly</code> <code>--mode=debug</code> <code>--lines=5</code>",
+        );
+
+        let mut flags = Flags {
+            mode: Some(Mode::Release),
+            ..Flags::default()
+        };
+        config.apply(&mut flags);
+        assert_eq!(
+            flags,
+            Flags {
+                channel: Some(Channel::Nightly),
+                mode: Some(Mode::Release),
+                max_lines: Some(5),
+                ..Flags::default()
+            },
+        );
+    }
+
+    #[test]
+    fn test_chat_config_nightly_only() {
+        let config = ChatConfig {
+            channel: Some(Channel::Stable),
+            ..ChatConfig::default()
+        };
+        let mut flags = Flags {
+            task: Some(Task::Miri),
+            ..Flags::default()
+        };
+        config.apply(&mut flags);
+        assert_eq!(flags.channel, None);
+        let mut flags = Flags {
+            channel: Some(Channel::Beta),
+            task: Some(Task::Expand),
+            ..Flags::default()
+        };
+        config.apply(&mut flags);
+        assert_eq!(flags.channel, Some(Channel::Beta));
+    }
+
+    #[test]
+    fn test_chat_config_from_flags() {
+        let flags = Flags {
+            edition: Some(Edition::E2024),
+            ..Flags::default()
+        };
+        assert_eq!(
+            ChatConfig::from_flags(&flags),
+            Some(ChatConfig {
+                edition: Some(Edition::E2024),
+                ..ChatConfig::default()
+            }),
+        );
+        let flags = Flags {
+            bare: true,
+            ..Flags::default()
+        };
+        assert_eq!(ChatConfig::from_flags(&flags), None);
+    }
+}
@@ -0,0 +1,189 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// A diagnostic from rustc or clippy.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Diagnostic<'a> {
+    pub level: Level,
+    /// Error code, e.g. `E0425`.
+    pub code: Option<&'a str>,
+    pub message: &'a str,
+    /// Primary location of the diagnostic.
+    pub location: Option<Location>,
+    /// Lint which triggers the diagnostic, e.g. `clippy::redundant_clone`.
+    pub lint: Option<&'a str>,
+    /// Help messages attached to the diagnostic.
+    pub helps: Vec<&'a str>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Parse diagnostics from the stderr of cargo.
+pub fn parse(stderr: &str) -> Vec<Diagnostic<'_>> {
+    static RE_HEADER: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^(error|warning)(?:\[(\w+)\])?: (.+)$").unwrap());
+    static RE_LOCATION: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^\s*--> src/(?:main|lib)\.rs:(\d+):(\d+)").unwrap());
+    static RE_LINT: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"#\[(?:warn|deny|forbid)\(((?:clippy::)?\w+)\)\]").unwrap());
+    static RE_HELP: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*(?:= )?help: (.+)$").unwrap());
+
+    let mut result: Vec<Diagnostic<'_>> = vec![];
+    let mut current = None;
+    for line in stderr.lines() {
+        if let Some(captures) = RE_HEADER.captures(line) {
+            result.extend(current.take());
+            let message = captures.get(3).unwrap().as_str();
+            if is_summary(message) {
+                continue;
+            }
+            current = Some(Diagnostic {
+                level: match captures.get(1).unwrap().as_str() {
+                    "error" => Level::Error,
+                    _ => Level::Warning,
+                },
+                code: captures.get(2).map(|m| m.as_str()),
+                message,
+                location: None,
+                lint: None,
+                helps: vec![],
+            });
+            continue;
+        }
+        let diagnostic = match &mut current {
+            Some(diagnostic) => diagnostic,
+            None => continue,
+        };
+        if diagnostic.location.is_none() {
+            if let Some(captures) = RE_LOCATION.captures(line) {
+                let number = |i| captures.get(i).unwrap().as_str().parse().unwrap_or(0);
+                diagnostic.location = Some(Location {
+                    line: number(1),
+                    column: number(2),
+                });
+            }
+        }
+        if diagnostic.lint.is_none() {
+            if let Some(captures) = RE_LINT.captures(line) {
+                diagnostic.lint = Some(captures.get(1).unwrap().as_str());
+            }
+        }
+        if let Some(captures) = RE_HELP.captures(line) {
+            diagnostic.helps.push(captures.get(1).unwrap().as_str());
+        }
+    }
+    result.extend(current);
+    result
+}
+
+/// Check whether the message is a summary from cargo or rustc
+/// rather than a real diagnostic.
+fn is_summary(message: &str) -> bool {
+    static RE_SUMMARY: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(concat!(
+            r"^(?:aborting due to |could not compile |build failed|",
+            r"`playground` \(.+\) generated |",
+            r"\d+ warnings? emitted)"
+        ))
+        .unwrap()
+    });
+    RE_SUMMARY.is_match(message)
+}
+
+/// Get the URL to the documentation of the given clippy lint.
+pub fn clippy_lint_url(lint: &str) -> Option<String> {
+    let name = lint.strip_prefix("clippy::")?;
+    Some(format!(
+        "https://rust-lang.github.io/rust-clippy/master/index.html#{name}"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let stderr = "    Checking playground v0.0.1 (/playground)
+warning: unused variable: `x`
+ --> src/main.rs:2:9
+  |
+2 |     let x = 1;
+  |         ^ help: if this is intentional, prefix it with an underscore: `_x`
+  |
+  = note: `#[warn(unused_variables)]` on by default
+
+error[E0425]: cannot find value `y` in this scope
+ --> src/main.rs:3:5
+  |
+3 |     y
+  |     ^ not found in this scope
+
+warning: redundant clone
+ --> src/main.rs:4:14
+  |
+4 |     let z = v.clone();
+  |              ^^^^^^^^ help: remove this
+  |
+  = help: for further information visit https://rust-lang.github.io/rust-clippy/master/index.html#redundant_clone
+  = note: `#[warn(clippy::redundant_clone)]` on by default
+
+warning: `playground` (bin \"playground\") generated 2 warnings
+error: could not compile `playground` (bin \"playground\") due to previous error; 2 warnings emitted
+";
+        assert_eq!(
+            parse(stderr),
+            vec![
+                Diagnostic {
+                    level: Level::Warning,
+                    code: None,
+                    message: "unused variable: `x`",
+                    location: Some(Location { line: 2, column: 9 }),
+                    lint: Some("unused_variables"),
+                    helps: vec![],
+                },
+                Diagnostic {
+                    level: Level::Error,
+                    code: Some("E0425"),
+                    message: "cannot find value `y` in this scope",
+                    location: Some(Location { line: 3, column: 5 }),
+                    lint: None,
+                    helps: vec![],
+                },
+                Diagnostic {
+                    level: Level::Warning,
+                    code: None,
+                    message: "redundant clone",
+                    location: Some(Location {
+                        line: 4,
+                        column: 14
+                    }),
+                    lint: Some("clippy::redundant_clone"),
+                    helps: vec![
+                        "for further information visit \
+                         https://rust-lang.github.io/rust-clippy/master/index.html#redundant_clone",
+                    ],
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn test_clippy_lint_url() {
+        assert_eq!(
+            clippy_lint_url("clippy::redundant_clone").as_deref(),
+            Some("https://rust-lang.github.io/rust-clippy/master/index.html#redundant_clone"),
+        );
+        assert_eq!(clippy_lint_url("unused_variables"), None);
+    }
+}
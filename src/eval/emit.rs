@@ -0,0 +1,457 @@
+use super::parse::Target;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+
+/// Items defined in the prelude, which are not interesting to users.
+const PRELUDE_ITEMS: &[&str] = &[
+    "type_name_of_val",
+    "__EvalSkipUnit",
+    "__EvalPrintable",
+    "__eval_printable",
+];
+
+/// Filter the compiler output down to the functions defined by the user.
+///
+/// `template` is the code put before the user's code, if any.
+pub fn filter_output(output: &str, target: Target, template: Option<&str>) -> String {
+    match target {
+        Target::Asm => filter_asm(output),
+        Target::LlvmIr => filter_llvm_ir(output),
+        Target::Mir | Target::Hir => strip_prelude(output, template),
+    }
+}
+
+/// Check whether the demangled symbol belongs to the user's crate.
+fn is_user_symbol(symbol: &str) -> bool {
+    let symbol = symbol.trim_start_matches('<');
+    match symbol.strip_prefix("playground::") {
+        Some(name) => !PRELUDE_ITEMS.iter().any(|f| name.starts_with(f)),
+        None => false,
+    }
+}
+
+/// Keep only blocks of the user's functions in the assembly.
+///
+/// A block starts with a label at the beginning of a line. Local labels
+/// (those starting with `.`) are considered part of the current block.
+fn filter_asm(output: &str) -> String {
+    let mut result = String::new();
+    let mut keep = false;
+    for line in output.lines() {
+        if !line.starts_with(char::is_whitespace) && !line.starts_with('.') {
+            if let Some(label) = line.strip_suffix(':') {
+                keep = is_user_symbol(label);
+                if keep && !result.is_empty() {
+                    result.push('\n');
+                }
+            }
+        }
+        if keep {
+            result.push_str(line);
+            result.push('\n');
+        }
+    }
+    result
+}
+
+/// Keep only definitions of the user's functions in the LLVM IR,
+/// with their names demangled.
+fn filter_llvm_ir(output: &str) -> String {
+    static RE_DEFINE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^define .*?@([\w.$]+)\(").unwrap());
+    let mut result = String::new();
+    let mut keep = false;
+    for line in output.lines() {
+        if let Some(captures) = RE_DEFINE.captures(line) {
+            let symbol = captures.get(1).unwrap().as_str();
+            keep = demangle(symbol).is_some_and(|s| is_user_symbol(&s));
+            if keep && !result.is_empty() {
+                result.push('\n');
+            }
+        }
+        if keep {
+            result.push_str(&demangle_all(line));
+            result.push('\n');
+            if line == "}" {
+                keep = false;
+            }
+        }
+    }
+    result
+}
+
+/// Remove the code injected around the user's code from output which looks like source code,
+/// e.g. MIR, HIR, and macro expansion.
+///
+/// `template` is the code put before the user's code, if any.
+/// Only imports and attributes coming from it are removed,
+/// so that the same ones written by the user are kept.
+pub fn strip_prelude(output: &str, template: Option<&str>) -> String {
+    // Methods of impls in MIR are named after where the impls are.
+    static RE_ITEM: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^(?:pub )?(?:fn (?:<impl at [^>]*>::)?|trait |impl\s*(?:<[^{]*?>)?\s+)(\w+)")
+            .unwrap()
+    });
+    // Lines injected by the compiler, and attributes injected along with the line following.
+    const INJECTED_LINES: &[&str] = &["#![feature(prelude_import)]", "extern crate std;"];
+    const INJECTED_ATTRS: &[&str] = &["#[prelude_import]", "#[attr = PreludeImport]"];
+    // Number of times each line can still be removed.
+    let mut removable = HashMap::<String, usize>::new();
+    let mut add_removable = |line: String| *removable.entry(line).or_default() += 1;
+    INJECTED_LINES
+        .iter()
+        .for_each(|line| add_removable(line.to_string()));
+    for line in template.into_iter().flat_map(str::lines).map(str::trim) {
+        if line.starts_with("#![") || line.starts_with("extern crate ") {
+            add_removable(line.to_string());
+        } else if line.starts_with("use ") {
+            add_removable(line.to_string());
+            // HIR has imports of a group split into one for each name.
+            flatten_use(line).into_iter().for_each(&mut add_removable);
+        }
+    }
+    let mut remove = |line: &str| match removable.get_mut(line) {
+        Some(count) if *count > 0 => {
+            *count -= 1;
+            true
+        }
+        _ => false,
+    };
+    let mut result = String::new();
+    // Depth of braces in the prelude item being skipped,
+    // and whether its body has started.
+    let mut skipping_item = None;
+    let mut lines = output.lines().peekable();
+    while let Some(line) = lines.next() {
+        if skipping_item.is_none() && template.is_some() {
+            let name = RE_ITEM.captures(line).map(|c| c.get(1).unwrap().as_str());
+            if name.is_some_and(|name| PRELUDE_ITEMS.contains(&name)) {
+                skipping_item = Some((0usize, false));
+            }
+        }
+        if let Some((depth, started)) = &mut skipping_item {
+            for c in line.chars() {
+                match c {
+                    '{' => {
+                        *depth += 1;
+                        *started = true;
+                    }
+                    '}' => *depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+            }
+            if *started && *depth == 0 {
+                skipping_item = None;
+            }
+            continue;
+        }
+        if INJECTED_ATTRS.contains(&line) {
+            // The prelude import of the edition.
+            lines.next();
+            continue;
+        }
+        if line == "#[macro_use]" && lines.peek() == Some(&"extern crate std;") {
+            continue;
+        }
+        if remove(line) {
+            continue;
+        }
+        if line.is_empty() && (result.is_empty() || result.ends_with("\n\n")) {
+            continue;
+        }
+        result.push_str(line);
+        result.push('\n');
+    }
+    result
+}
+
+/// Split an import of a group into imports of each name, as printed in HIR,
+/// along with the import of the group itself.
+fn flatten_use(line: &str) -> Vec<String> {
+    let (prefix, group) = match line
+        .strip_prefix("use ")
+        .and_then(|s| s.strip_suffix("};"))
+        .and_then(|s| s.split_once("::{"))
+    {
+        Some(parts) => parts,
+        None => return vec![],
+    };
+    if group.contains('{') {
+        return vec![];
+    }
+    let names = group
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty());
+    let imports = names.map(|name| match name {
+        "self" => format!("use {prefix};"),
+        name => format!("use {prefix}::{name};"),
+    });
+    Some("use ::{};".to_string())
+        .into_iter()
+        .chain(imports)
+        .collect()
+}
+
+/// Demangle all legacy mangled symbols in the given text.
+fn demangle_all(text: &str) -> String {
+    static RE_SYMBOL: Lazy<Regex> = Lazy::new(|| Regex::new(r"_ZN[\w.$]+E").unwrap());
+    RE_SYMBOL
+        .replace_all(text, |captures: &Captures<'_>| {
+            let symbol = captures.get(0).unwrap().as_str();
+            match demangle(symbol) {
+                Some(demangled) => format!("\"{demangled}\""),
+                None => symbol.to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// Demangle a symbol in the legacy mangling scheme, dropping the hash.
+fn demangle(symbol: &str) -> Option<String> {
+    let mut rest = symbol.strip_prefix("_ZN")?.strip_suffix('E')?;
+    let mut parts = vec![];
+    while !rest.is_empty() {
+        let len_end = rest.find(|c: char| !c.is_ascii_digit())?;
+        let len = rest[..len_end].parse::<usize>().ok()?;
+        let part = rest.get(len_end..len_end + len)?;
+        rest = &rest[len_end + len..];
+        // The last part is the hash.
+        if rest.is_empty() && part.starts_with('h') && part.len() == 17 {
+            break;
+        }
+        parts.push(demangle_part(
+            part.strip_prefix('_')
+                .filter(|p| p.starts_with('$'))
+                .unwrap_or(part),
+        ));
+    }
+    Some(parts.join("::"))
+}
+
+fn demangle_part(part: &str) -> String {
+    const ESCAPES: &[(&str, &str)] = &[
+        ("$SP$", "@"),
+        ("$BP$", "*"),
+        ("$RF$", "&"),
+        ("$LT$", "<"),
+        ("$GT$", ">"),
+        ("$LP$", "("),
+        ("$RP$", ")"),
+        ("$C$", ","),
+        ("$u20$", " "),
+        ("$u27$", "'"),
+        ("$u5b$", "["),
+        ("$u5d$", "]"),
+        ("$u7b$", "{"),
+        ("$u7d$", "}"),
+        ("$u7e$", "~"),
+        ("..", "::"),
+    ];
+    let mut result = part.to_string();
+    for (from, to) in ESCAPES {
+        result = result.replace(from, to);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_demangle() {
+        assert_eq!(
+            demangle("_ZN10playground6square17h0123456789abcdefE").as_deref(),
+            Some("playground::square"),
+        );
+        assert_eq!(
+            demangle("_ZN4core3ptr85drop_in_place$LT$std..rt..lang_start$LT$$LP$$RP$$GT$..$u7b$$u7b$closure$u7d$$u7d$$GT$17h0123456789abcdefE").as_deref(),
+            Some("core::ptr::drop_in_place<std::rt::lang_start<()>::{{closure}}>"),
+        );
+        assert_eq!(demangle("main"), None);
+    }
+
+    #[test]
+    fn test_filter_asm() {
+        let output = "\
+std::rt::lang_start:
+\tpush\trax
+\tmov\teax, ecx
+\tmov\trcx, rdx
+\tmov\trdx, rsi
+\tmov\tqword ptr [rsp], rdi
+\tmov\trdi, rsp
+\tlea\trsi, [rip + .Lanon.a986f7d00de1b10bdad60be03787757a.3]
+\tmovzx\tr8d, al
+\tcall\tqword ptr [rip + std::rt::lang_start_internal@GOTPCREL]
+\tpop\trcx
+\tret
+
+playground::square:
+\tpush\trax
+\timul\tedi, edi
+\tmov\tdword ptr [rsp + 4], edi
+\tseto\tal
+\tjo\t.LBB1_2
+\tmov\teax, dword ptr [rsp + 4]
+\tpop\trcx
+\tret
+.LBB1_2:
+\tlea\trdi, [rip + .Lanon.a986f7d00de1b10bdad60be03787757a.2]
+\tcall\tqword ptr [rip + core::panicking::panic_const::panic_const_mul_overflow@GOTPCREL]
+
+playground::type_name_of_val:
+\tlea\trax, [rip + .Lanon.a986f7d00de1b10bdad60be03787757a.4]
+\tmov\tedx, 3
+\tret
+";
+        assert_eq!(
+            filter_output(output, Target::Asm, None),
+            "\
+playground::square:
+\tpush\trax
+\timul\tedi, edi
+\tmov\tdword ptr [rsp + 4], edi
+\tseto\tal
+\tjo\t.LBB1_2
+\tmov\teax, dword ptr [rsp + 4]
+\tpop\trcx
+\tret
+.LBB1_2:
+\tlea\trdi, [rip + .Lanon.a986f7d00de1b10bdad60be03787757a.2]
+\tcall\tqword ptr [rip + core::panicking::panic_const::panic_const_mul_overflow@GOTPCREL]
+
+",
+        );
+    }
+
+    #[test]
+    fn test_filter_llvm_ir() {
+        let output = "\
+; ModuleID = 'playground.7b2f4e0d-cgu.0'
+define internal void @_ZN4core3ptr13drop_in_place17h0123456789abcdefE(ptr %_1) {
+start:
+  ret void
+}
+
+define i32 @_ZN10playground6square17h0123456789abcdefE(i32 %x) unnamed_addr {
+start:
+  %0 = mul i32 %x, %x
+  ret i32 %0
+}
+
+declare void @llvm.trap()
+";
+        assert_eq!(
+            filter_output(output, Target::LlvmIr, None),
+            "define i32 @\"playground::square\"(i32 %x) unnamed_addr {\n\
+             start:\n  %0 = mul i32 %x, %x\n  ret i32 %0\n}\n",
+        );
+    }
+
+    #[test]
+    fn test_filter_hir() {
+        let output = "\
+#[prelude_import]
+use std::prelude::rust_2021::*;
+#[macro_use]
+extern crate std;
+use std::any::{type_name, Any};
+
+fn type_name_of_val<T: ?Sized>(_: &T) -> &'static str {
+    type_name::<T>()
+}
+
+fn square(x: i32) -> i32 { x * x }
+";
+        let template = "use std::any::{type_name, Any};\n\n\
+                        fn type_name_of_val<T: ?Sized>(_: &T) -> &'static str {\n}\n";
+        assert_eq!(
+            filter_output(output, Target::Hir, Some(template)),
+            "fn square(x: i32) -> i32 { x * x }\n",
+        );
+    }
+
+    #[test]
+    fn test_filter_mir() {
+        let output = "\
+// WARNING: This output format is intended for human consumers only
+fn <impl at src/lib.rs:45:1: 45:31>::__eval_printable(_1: &(&(),)) -> Option<&str> {
+    debug self => _1;
+    let mut _0: std::option::Option<&str>;
+
+    bb0: {
+        _0 = Option::<&str>::None;
+        return;
+    }
+}
+
+fn square(_1: i32) -> i32 {
+    debug x => _1;
+}
+";
+        assert_eq!(
+            filter_output(output, Target::Mir, Some("")),
+            "// WARNING: This output format is intended for human consumers only\n\n\
+             fn square(_1: i32) -> i32 {\n    debug x => _1;\n}\n",
+        );
+    }
+
+    #[test]
+    fn test_strip_prelude() {
+        let template = "#![allow(dead_code)]\nextern crate once_cell;\n\
+                        use std::fmt::{self, Debug};\nuse std::ops::*;\nuse std::rc::Rc;\n";
+        // Imports of the prelude are split in HIR.
+        let output = "\
+#![allow(dead_code)]
+#![feature(test)]
+extern crate std;
+#[attr = PreludeImport]
+use std::prelude::rust_2021::*;
+extern crate once_cell;
+use ::{};
+use std::fmt;
+use std::fmt::Debug;
+use std::ops::*;
+use std::rc::Rc;
+fn type_name_of_val<T>(_: &'_ T) -> &'static str where
+    T: ?Sized { type_name::<T>() }
+trait __EvalSkipUnit {
+    fn __eval_printable(&self)
+    -> Option<&'static str>;
+}
+impl  __EvalSkipUnit for (&'_ (),) {
+    fn __eval_printable(&self) -> Option<&'static str> { None }
+}
+impl <'a, T> __EvalPrintable<'a, T> for &'_ (&'a T,) where T: ?Sized
+    {
+    fn __eval_printable(&self) -> Option<&'a T> { Some(self.0) }
+}
+use std::ops::*;
+use ::{};
+use std::cmp::Reverse;
+fn main() { }
+";
+        assert_eq!(
+            strip_prelude(output, Some(template)),
+            "#![feature(test)]\nuse std::ops::*;\nuse ::{};\n\
+             use std::cmp::Reverse;\nfn main() { }\n",
+        );
+        // Imports of the user are kept if there is no prelude.
+        let output = "\
+#![feature(prelude_import)]
+extern crate std;
+#[prelude_import]
+use std::prelude::rust_2021::*;
+use std::rc::Rc;
+fn type_name_of_val<T: ?Sized>(_: &T) -> &'static str { type_name::<T>() }
+fn main() {}
+";
+        assert_eq!(
+            strip_prelude(output, None),
+            "use std::rc::Rc;\nfn type_name_of_val<T: ?Sized>(_: &T) -> &'static str \
+             { type_name::<T>() }\nfn main() {}\n",
+        );
+    }
+}
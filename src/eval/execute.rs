@@ -1,44 +1,172 @@
+use super::diagnostic::{self, Diagnostic, Level};
 use super::parse::Flags;
-use crate::eval::parse::{get_help_message, Channel, Mode};
+use super::source_map::{CodeBuilder, SourceMap};
+use super::syntax::{self, Parsed};
+use super::{emit, libtest};
+use crate::eval::parse::{get_help_message, Channel, Edition, Mode, Output, Target, Task};
 use crate::utils;
 use futures::{future, FutureExt as _};
 use htmlescape::{encode_attribute, encode_minimal};
 use log::{debug, warn};
-use once_cell::sync::Lazy;
+use once_cell::sync::{Lazy, OnceCell};
+use parking_lot::Mutex;
 use regex::{Captures, Regex};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
 use std::future::Future;
+use std::time::{Duration, Instant};
 
 pub fn execute<'p>(
     client: &'p Client,
     content: &'p str,
+    stdin: Option<&'p str>,
     flags: Flags,
     is_private: bool,
 ) -> Option<impl Future<Output = Result<String, reqwest::Error>> + 'p> {
+    let limits = Limits {
+        full: flags.output.map_or(is_private, |o| o == Output::Full),
+        max_lines: flags.max_lines,
+    };
     Some(if flags.help {
         future::ok(get_help_message()).left_future().left_future()
     } else if flags.version {
-        let channel = flags.channel;
-        get_version(client, channel).right_future().left_future()
+        get_version(client, flags.channel, flags.edition)
+            .right_future()
+            .left_future()
     } else if !content.trim().is_empty() {
-        run_code(client, content, flags, is_private).right_future()
+        if flags.all_channels {
+            run_on_all_channels(client, content, stdin, flags, limits)
+                .left_future()
+                .right_future()
+        } else {
+            run_code(client, content, stdin, flags, limits)
+                .right_future()
+                .right_future()
+        }
     } else {
         return None;
     })
 }
 
-async fn get_version(client: &Client, channel: Option<Channel>) -> Result<String, reqwest::Error> {
-    let url = format!(
-        "https://play.rust-lang.org/meta/version/{}",
-        channel.unwrap_or(Channel::Stable).as_str(),
-    );
-    let resp = client.get(&url).send().await?;
-    let v: Version = resp.error_for_status()?.json().await?;
-    Ok(format!("rustc {} ({:.9} {})", v.version, v.hash, v.date))
+async fn get_version(
+    client: &Client,
+    channel: Option<Channel>,
+    edition: Option<Edition>,
+) -> Result<String, reqwest::Error> {
+    let channel = channel.unwrap_or(Channel::Stable);
+    let edition = edition.unwrap_or_default();
+    let versions = fetch_versions(client).await?;
+    let v = match check_channel(&versions, channel, edition) {
+        Ok(v) => v,
+        Err(err) => return Ok(err),
+    };
+    Ok(format!(
+        "rustc {} ({:.9} {}), edition {}",
+        v.version,
+        v.hash,
+        v.date,
+        edition.as_str(),
+    ))
 }
 
+/// Versions of rustc on the channels the playground provides.
+type Versions = HashMap<Channel, Version>;
+
+/// Fetch the versions of rustc on all channels the playground provides.
+///
+/// Versions are cached for a while, since they only change daily at most.
+async fn fetch_versions(client: &Client) -> Result<Versions, reqwest::Error> {
+    static CACHE: Lazy<Mutex<Option<(Instant, Versions)>>> = Lazy::new(Default::default);
+    const CACHE_DURATION: Duration = Duration::from_secs(60 * 60);
+    if let Some((time, versions)) = &*CACHE.lock() {
+        if time.elapsed() < CACHE_DURATION {
+            return Ok(versions.clone());
+        }
+    }
+    const URL: &str = "https://play.rust-lang.org/meta/versions";
+    let resp = client.get(URL).send().await?;
+    let versions = parse_versions(resp.error_for_status()?.json().await?);
+    *CACHE.lock() = Some((Instant::now(), versions.clone()));
+    Ok(versions)
+}
+
+/// Versions of tools on a channel, as reported by the playground.
 #[derive(Deserialize)]
+struct ChannelVersions {
+    rustc: Version,
+}
+
+/// Take the versions of rustc on channels we know about from the report of the playground.
+fn parse_versions(channels: HashMap<String, ChannelVersions>) -> Versions {
+    let channels = channels.into_iter().filter_map(|(name, versions)| {
+        let channel = Channel::from_name(&name)?;
+        Some((channel, versions.rustc))
+    });
+    channels.collect()
+}
+
+/// Check whether the channel is provided by the playground
+/// and supports the edition, returning the version of rustc on it.
+fn check_channel(
+    versions: &Versions,
+    channel: Channel,
+    edition: Edition,
+) -> Result<&Version, String> {
+    let version = versions.get(&channel).ok_or_else(|| {
+        format!(
+            "error: {} channel is not available on the playground",
+            channel.as_str(),
+        )
+    })?;
+    check_edition(version, channel, edition)?;
+    Ok(version)
+}
+
+/// Check whether the edition is supported by the given version of rustc.
+fn check_edition(version: &Version, channel: Channel, edition: Edition) -> Result<(), String> {
+    let supported = match parse_version(&version.version) {
+        Some(v) => v >= edition.min_version(),
+        // Let rustc report the error if any.
+        None => true,
+    };
+    if supported {
+        return Ok(());
+    }
+    Err(format!(
+        "error: edition {} is not supported by rustc {} on {} channel",
+        edition.as_str(),
+        version.version,
+        channel.as_str(),
+    ))
+}
+
+/// Parse the major and minor numbers from a version like `1.85.0-nightly`.
+fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let mut parts = version.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+/// How much of the output to show.
+#[derive(Clone, Copy, Debug)]
+struct Limits {
+    /// Whether to show the full output.
+    full: bool,
+    /// Maximum number of lines in short output, overriding the default.
+    max_lines: Option<usize>,
+}
+
+impl Limits {
+    fn max_lines(self, default: usize) -> usize {
+        self.max_lines.unwrap_or(default)
+    }
+}
+
+#[derive(Clone, Deserialize)]
 struct Version {
     date: String,
     hash: String,
@@ -48,79 +176,967 @@ struct Version {
 async fn run_code(
     client: &Client,
     code: &str,
+    stdin: Option<&str>,
     flags: Flags,
-    is_private: bool,
+    limits: Limits,
+) -> Result<String, reqwest::Error> {
+    let channel = match flags.task {
+        Some(task) if task.is_nightly_only() => Channel::Nightly,
+        _ => flags.channel.unwrap_or(Channel::Stable),
+    };
+    match fetch_versions(client).await {
+        Ok(versions) => {
+            let edition = flags.edition.unwrap_or_default();
+            if let Err(err) = check_channel(&versions, channel, edition) {
+                return Ok(err);
+            }
+        }
+        // Let the playground report the error if any.
+        Err(err) => warn!("failed to fetch versions: {:?}", err),
+    }
+    let code = normalize_code(code, &flags);
+    // Input for running code is checked along with generating the code.
+    if stdin.is_some() && !matches!(flags.task, None | Some(Task::Test)) {
+        return Ok(STDIN_UNSUPPORTED.to_string());
+    }
+    match flags.task {
+        None | Some(Task::Test) => execute_code(client, &code, stdin, flags, limits).await,
+        Some(Task::Check) => check_code(client, &code, flags, limits).await,
+        Some(Task::Clippy) => lint_code(client, &code, flags, limits).await,
+        Some(Task::Miri) => run_miri(client, &code, flags, limits).await,
+        Some(Task::Format) => format_code(client, &code, flags, limits).await,
+        Some(Task::Emit(target)) => emit_code(client, &code, flags, target, limits).await,
+        Some(Task::Expand) => expand_code(client, &code, flags, limits).await,
+        Some(Task::Size) => show_size(client, &code, flags, limits).await,
+    }
+}
+
+const STDIN_UNSUPPORTED: &str = "error: input for stdin is only supported when running the code";
+
+/// Check whether input can be fed into stdin of the program of the code,
+/// returning the error if not.
+///
+/// The input is fed by the program running itself again from its `main`,
+/// so it only works when running code with `main` or code wrapped in one.
+fn check_stdin(parsed: &Parsed<'_>, flags: &Flags) -> Option<&'static str> {
+    match flags.task {
+        None if flags.bare && parsed.find_main().is_none() => {
+            Some("error: input for stdin needs <code>fn main()</code> with <code>--bare</code>")
+        }
+        None => None,
+        Some(_) => Some(STDIN_UNSUPPORTED),
+    }
+}
+
+/// Run the code on all channels concurrently,
+/// and show the results grouped by channels giving the same result.
+///
+/// A channel failing to run doesn't hide the results of the others.
+async fn run_on_all_channels(
+    client: &Client,
+    code: &str,
+    stdin: Option<&str>,
+    flags: Flags,
+    limits: Limits,
 ) -> Result<String, reqwest::Error> {
-    let code = utils::normalize_unicode_chars(code);
-    let code = generate_code_to_send(&code, flags.bare);
+    const CHANNELS: [Channel; 3] = [Channel::Stable, Channel::Beta, Channel::Nightly];
+    let versions = match fetch_versions(client).await {
+        Ok(versions) => versions,
+        Err(err) => {
+            warn!("failed to fetch versions: {:?}", err);
+            Versions::new()
+        }
+    };
+    // Run on the channels the playground provides, or all of them if unknown.
+    let channels: Vec<_> = CHANNELS
+        .into_iter()
+        .filter(|channel| versions.is_empty() || versions.contains_key(channel))
+        .collect();
+    let runs = channels.iter().map(|&channel| {
+        let flags = Flags {
+            channel: Some(channel),
+            ..flags.clone()
+        };
+        run_code(client, code, stdin, flags, limits)
+    });
+    let results = future::join_all(runs).await;
+    let results = channels.into_iter().zip(results);
+    Ok(group_channel_results(results.map(|(channel, result)| {
+        let result = result.unwrap_or_else(|err| super::describe_error(&err));
+        let version = versions.get(&channel).map(|v| v.version.clone());
+        (channel, version, result)
+    })))
+}
+
+/// Group the results of channels, each with its version if known, by the result.
+fn group_channel_results(
+    results: impl Iterator<Item = (Channel, Option<String>, String)>,
+) -> String {
+    // Results may only differ in links to documentation of the channel.
+    let normalize = |channel: Channel, result: &str| {
+        let doc = format!("doc.rust-lang.org/{}/", channel.as_str());
+        result.replace(&doc, "doc.rust-lang.org/")
+    };
+    let mut groups: Vec<(Vec<String>, String, String)> = vec![];
+    for (channel, version, result) in results {
+        let key = normalize(channel, &result);
+        let label = match version {
+            Some(version) => format!("{} ({})", channel.as_str(), encode_minimal(&version)),
+            None => channel.as_str().to_string(),
+        };
+        match groups.iter_mut().find(|(_, k, _)| *k == key) {
+            Some((labels, _, _)) => labels.push(label),
+            None => groups.push((vec![label], key, result)),
+        }
+    }
+    let groups = groups
+        .into_iter()
+        .map(|(labels, _, result)| format!("<b>{}</b>: {}", labels.join(", "), result.trim()));
+    groups.collect::<Vec<_>>().join("\n")
+}
+
+/// Get the code to share on the playground, which runs the same as evaluated.
+pub async fn code_to_share(code: &str, stdin: Option<&str>, flags: &Flags) -> String {
+    let code = normalize_code(code, flags);
+    match flags.task {
+        Some(Task::Test) => generate_code(&code, flags, CrateType::Lib, None).await.0,
+        Some(Task::Size) => match size_code(&code, flags.niche).await {
+            Some(code) => {
+                generate_code(&code, &Flags::default(), CrateType::Bin, None)
+                    .await
+                    .0
+            }
+            None => code.into_owned(),
+        },
+        _ => generate_code(&code, flags, CrateType::Bin, stdin).await.0,
+    }
+}
+
+fn normalize_code<'a>(code: &'a str, flags: &Flags) -> Cow<'a, str> {
+    if flags.raw {
+        code.into()
+    } else {
+        utils::normalize_unicode_chars(code)
+    }
+}
+
+async fn post_json<T>(client: &Client, url: &str, req: &impl Serialize) -> Result<T, reqwest::Error>
+where
+    for<'de> T: Deserialize<'de>,
+{
+    let resp = client.post(url).json(req).send().await?;
+    resp.error_for_status()?.json().await
+}
+
+async fn execute_code(
+    client: &Client,
+    code: &str,
+    stdin: Option<&str>,
+    flags: Flags,
+    limits: Limits,
+) -> Result<String, reqwest::Error> {
+    let is_test = flags.task == Some(Task::Test);
+    let crate_type = if is_test {
+        CrateType::Lib
+    } else {
+        CrateType::Bin
+    };
+    let generated = {
+        let flags = flags.clone();
+        let stdin = stdin.map(str::to_string);
+        parse_code(code, move |parsed| {
+            if let Some(err) = stdin.as_ref().and_then(|_| check_stdin(parsed, &flags)) {
+                return Err(err);
+            }
+            Ok(generate_code_with_stdin(
+                parsed,
+                &flags,
+                crate_type,
+                stdin.as_deref(),
+            ))
+        })
+        .await
+    };
+    let (code, source_map) = match generated {
+        Ok(generated) => generated,
+        Err(err) => return Ok(err.to_string()),
+    };
     let channel = flags.channel.unwrap_or(Channel::Stable);
     let req = Request {
         channel,
-        edition: flags.edition.unwrap_or("2021"),
+        edition: flags.edition.unwrap_or_default(),
+        mode: flags.mode.unwrap_or(Mode::Debug),
+        crate_type,
+        tests: is_test,
+        backtrace: false,
+        code,
+    };
+    const URL: &str = "https://play.rust-lang.org/execute";
+    let mut resp: Response = post_json(client, URL, &req).await?;
+    resp.stderr = source_map.rewrite_locations(&resp.stderr).into_owned();
+    if is_test {
+        // Panics of tests are captured into stdout.
+        resp.stdout = source_map.rewrite_locations(&resp.stdout).into_owned();
+    }
+    Ok(if is_test {
+        generate_test_result_from_response(resp, channel, limits)
+    } else {
+        generate_result_from_response(resp, channel, limits)
+    })
+}
+
+async fn lint_code(
+    client: &Client,
+    code: &str,
+    flags: Flags,
+    limits: Limits,
+) -> Result<String, reqwest::Error> {
+    let channel = flags.channel.unwrap_or(Channel::Stable);
+    let req = ClippyRequest {
+        channel,
+        edition: flags.edition.unwrap_or_default(),
+        crate_type: CrateType::Bin,
+        code: generate_code(code, &flags, CrateType::Bin, None).await.0,
+    };
+    const URL: &str = "https://play.rust-lang.org/clippy";
+    let resp = post_json(client, URL, &req).await?;
+    Ok(generate_clippy_result_from_response(resp, channel, limits))
+}
+
+async fn check_code(
+    client: &Client,
+    code: &str,
+    flags: Flags,
+    limits: Limits,
+) -> Result<String, reqwest::Error> {
+    let channel = flags.channel.unwrap_or(Channel::Stable);
+    let (code, source_map) = generate_code(code, &flags, CrateType::Bin, None).await;
+    // Emitting MIR goes through all the checks without running the code.
+    let req = CompileRequest {
+        target: Target::Mir,
+        assembly_flavor: "intel",
+        demangle_assembly: "demangle",
+        process_assembly: "filter",
+        channel,
+        edition: flags.edition.unwrap_or_default(),
         mode: flags.mode.unwrap_or(Mode::Debug),
         crate_type: CrateType::Bin,
         tests: false,
         backtrace: false,
         code,
     };
-    const URL: &str = "https://play.rust-lang.org/execute";
-    let resp = client.post(URL).json(&req).send().await?;
-    let resp = resp.error_for_status()?.json().await?;
-    Ok(generate_result_from_response(resp, channel, is_private))
+    const URL: &str = "https://play.rust-lang.org/compile";
+    let resp = post_json(client, URL, &req).await?;
+    Ok(generate_check_result_from_response(
+        resp,
+        &source_map,
+        channel,
+        limits,
+    ))
 }
 
+/// Attributes put before the code of the user.
+const HEADER: &str = "#![allow(dead_code)]\n#![allow(unused_imports)]\n";
+
 const PRELUDE: &str = include_str!("prelude.res.rs");
 
-fn generate_code_to_send(code: &str, bare: bool) -> String {
-    if bare || code.contains("fn main()") {
-        return code.to_string();
-    }
-    macro_rules! template {
-        ($($line:expr,)+) => {
-            concat!($($line, '\n',)+)
+const STDIN_FEEDER: &str = include_str!("stdin.res.rs");
+
+/// Parse the code, whose statements start after its headers,
+/// and run the function generating from it on the parser thread.
+async fn parse_code<T: Send + 'static>(
+    code: &str,
+    f: impl FnOnce(&Parsed<'_>) -> T + Send + 'static,
+) -> T {
+    let (header, _) = extract_code_headers(code);
+    syntax::parse(code.to_string(), header.len(), f).await
+}
+
+/// Generate the code to send on the parser thread, see `generate_code_with_stdin`.
+async fn generate_code(
+    code: &str,
+    flags: &Flags,
+    crate_type: CrateType,
+    stdin: Option<&str>,
+) -> (String, SourceMap) {
+    let flags = flags.clone();
+    let stdin = stdin.map(str::to_string);
+    parse_code(code, move |parsed| {
+        generate_code_with_stdin(parsed, &flags, crate_type, stdin.as_deref())
+    })
+    .await
+}
+
+/// Generate the code to send, which feeds the given input into stdin when run.
+///
+/// The playground doesn't take any input for stdin,
+/// so the program runs itself again with the input fed into the stdin of it.
+fn generate_code_with_stdin(
+    parsed: &Parsed<'_>,
+    flags: &Flags,
+    crate_type: CrateType,
+    stdin: Option<&str>,
+) -> (String, SourceMap) {
+    let code = parsed.code();
+    let mut builder = CodeBuilder::new(code);
+    let feed_stdin = stdin.map(|stdin| format!("__eval_feed_stdin({stdin:?});"));
+    // Only code run as a binary or fed with input needs its `main`.
+    let main_body = if crate_type == CrateType::Bin || feed_stdin.is_some() {
+        parsed.find_main()
+    } else {
+        None
+    };
+    if flags.bare || (crate_type == CrateType::Bin && main_body.is_some()) {
+        // Feed stdin at the beginning of the main function of the user.
+        match main_body.filter(|_| feed_stdin.is_some()) {
+            Some(body_start) => {
+                builder.push_user(0..body_start);
+                builder.push_template(feed_stdin.as_deref().unwrap());
+                builder.push_user(body_start..code.len());
+                builder.push_template("\n");
+                builder.push_template(STDIN_FEEDER);
+            }
+            None => builder.push_user(0..code.len()),
         }
+        return builder.finish();
     }
     let (header, body) = extract_code_headers(code);
     debug!("extract: {:?} -> ({:?}, {:?})", code, header, body);
-    let code = if body.contains("println!") || body.contains("print!") {
-        format!("{{\n{code}\n}};")
+    let body_range = header.len()..code.len();
+    builder.push_template(HEADER);
+    builder.push_user(0..header.len());
+    builder.push_template("\n");
+    builder.push_template(PRELUDE);
+    builder.push_template("\n");
+    if crate_type == CrateType::Lib {
+        // Library code consists of items, so it is put after the prelude as is.
+        builder.push_user(body_range);
+        builder.push_template("\n");
+        return builder.finish();
+    }
+    static PRELUDE_NAMES: OnceCell<HashSet<String>> = OnceCell::new();
+    let prelude_names =
+        PRELUDE_NAMES.get_or_init(|| parsed.parse_other(PRELUDE, 0).defined_names());
+    let structure = parsed.analyze_body(prelude_names);
+    debug!("structure: {:?}", structure);
+    let structure = match structure {
+        Some(structure) => structure,
+        // Print the value of the whole code if it fails to parse,
+        // and let the compiler report the error.
+        None => syntax::Body {
+            expr: Some(body_range.clone()),
+            ..Default::default()
+        },
+    };
+    let items = structure.items;
+    let expr = structure.expr;
+    // Items are put at the module level, so that they can refer to each other by path.
+    for item in items.iter() {
+        builder.push_template("\n");
+        builder.push_user(item.clone());
+    }
+    // Code awaiting at the top level runs in the tokio runtime, which the playground provides.
+    if structure.is_async {
+        builder.push_template("\n#[tokio::main]\nasync fn main()");
     } else {
-        format!(
-            template! {
-                // Template below would provide the indent of this line.
-                "println!(\"{{:?}}\", {{",
-                "        {code}",
-                "    }});",
-            },
-            code = body
+        builder.push_template("\nfn main()");
+    }
+    builder.push_template(" -> Result<(), Box<dyn std::error::Error>> {\n    ");
+    if let Some(feed_stdin) = &feed_stdin {
+        builder.push_template(feed_stdin);
+        builder.push_template("\n    ");
+    }
+    let stmts_end = expr.as_ref().map_or(body_range.end, |expr| expr.start);
+    let mut pos = body_range.start;
+    for item in items.iter() {
+        builder.push_user(pos..item.start);
+        pos = item.end;
+    }
+    builder.push_user(pos..stmts_end);
+    if let Some(expr) = expr {
+        let format = match (flags.display, flags.pretty) {
+            (false, false) => "{:?}",
+            (false, true) => "{:#?}",
+            (true, false) => "{}",
+            (true, true) => "{:#}",
+        };
+        let await_expr = if structure.expr_is_async {
+            ".await"
+        } else {
+            ""
+        };
+        let print = if flags.show_type {
+            format!("println!(\"{format}: {{}}\", __value, type_name_of_val(__value));")
+        } else {
+            format!("println!(\"{format}\", __value);")
+        };
+        // Calls may still return `()`, which is not worth printing,
+        // and is skipped by the prelude before it's formatted.
+        builder.push_template("\n    match {\n        ");
+        builder.push_user(expr.clone());
+        builder.push_template(&format!(
+            "\n    }}{await_expr} {{\n        \
+             __value => if let Some(__value) = (&(&__value,)).__eval_printable() {{\n            \
+             {print}\n        \
+             }},\n    }}\n    ",
+        ));
+        builder.push_user(expr.end..body_range.end);
+    }
+    builder.push_template("\n    Ok(())\n}\n");
+    if feed_stdin.is_some() {
+        builder.push_template(STDIN_FEEDER);
+    }
+    builder.finish()
+}
+
+fn generate_test_result_from_response(resp: Response, channel: Channel, limits: Limits) -> String {
+    let summary = match libtest::parse_output(&resp.stdout) {
+        Some(summary) => summary,
+        // Failed to compile.
+        None => return generate_result_from_response(resp, channel, limits),
+    };
+    let mut result = if summary.passed == 0 && summary.failed == 0 && summary.ignored == 0 {
+        "(no tests)".to_string()
+    } else {
+        let mut counts = vec![format!("{} passed", summary.passed)];
+        if summary.failed > 0 {
+            counts.push(format!("{} failed", summary.failed));
+        }
+        if summary.ignored > 0 {
+            counts.push(format!("{} ignored", summary.ignored));
+        }
+        counts.join(", ")
+    };
+    if limits.full {
+        let report = resp.stdout.trim();
+        write!(result, "\n<pre>{}</pre>", encode_minimal(report)).unwrap();
+        return result;
+    }
+    let failures = summary.failures.iter().map(|failure| {
+        let name = encode_minimal(failure.name);
+        match &failure.panic {
+            Some(panic) => {
+                const MAX_TOTAL_COLUMNS: usize = 100;
+                let panic = utils::truncate_output(panic, 1, MAX_TOTAL_COLUMNS);
+                format!(
+                    "<code>{}</code> panicked at {}",
+                    name,
+                    encode_minimal(&panic)
+                )
+            }
+            None => format!("<code>{name}</code> failed"),
+        }
+    });
+    for (i, failure) in failures.enumerate() {
+        result.push_str(if i == 0 { ": " } else { "; " });
+        result.push_str(&failure);
+    }
+    result
+}
+
+async fn show_size(
+    client: &Client,
+    types: &str,
+    flags: Flags,
+    limits: Limits,
+) -> Result<String, reqwest::Error> {
+    let code = match size_code(types, flags.niche).await {
+        Some(code) => code,
+        None => return Ok("error: expected types separated by commas".to_string()),
+    };
+    let channel = flags.channel.unwrap_or(Channel::Stable);
+    let req = Request {
+        channel,
+        edition: flags.edition.unwrap_or_default(),
+        mode: flags.mode.unwrap_or(Mode::Debug),
+        crate_type: CrateType::Bin,
+        tests: false,
+        backtrace: false,
+        code: generate_code(&code, &Flags::default(), CrateType::Bin, None)
+            .await
+            .0,
+    };
+    const URL: &str = "https://play.rust-lang.org/execute";
+    let resp: Response = post_json(client, URL, &req).await?;
+    if !resp.success {
+        return Ok(generate_result_from_response(resp, channel, limits));
+    }
+    // The table is always shown in full, since it's as long as the list of types.
+    Ok(format!(
+        "<pre>{}</pre>",
+        encode_minimal(resp.stdout.trim_end())
+    ))
+}
+
+/// Generate the code showing the size of the types on the parser thread.
+async fn size_code(types: &str, niche: bool) -> Option<String> {
+    let types = types.to_string();
+    syntax::parse(types, 0, move |parsed| generate_size_code(parsed, niche)).await
+}
+
+/// Generate code printing a table of the size and alignment of the types.
+fn generate_size_code(parsed: &Parsed<'_>, niche: bool) -> Option<String> {
+    let types = parsed.split_types()?;
+    let mut code = "let rows = [\n".to_string();
+    for ty in types {
+        writeln!(
+            code,
+            "    ({ty:?}, size_of::<{ty}>(), align_of::<{ty}>(), \
+             size_of::<Option<{ty}>>() == size_of::<{ty}>()),"
         )
+        .unwrap();
+    }
+    writeln!(code, "];\nlet niche = {niche};").unwrap();
+    code.push_str(
+        r#"let width = rows.iter().map(|row| row.0.chars().count()).max().unwrap_or(0).max(4);
+print!("{:width$}  size  align", "type");
+if niche {
+    print!("  niche");
+}
+println!();
+for (name, size, align, has_niche) in rows {
+    print!("{name:width$}  {size:>4}  {align:>5}");
+    if niche {
+        print!("  {:>5}", if has_niche { "yes" } else { "no" });
+    }
+    println!();
+}
+"#,
+    );
+    Some(code)
+}
+
+async fn run_miri(
+    client: &Client,
+    code: &str,
+    flags: Flags,
+    limits: Limits,
+) -> Result<String, reqwest::Error> {
+    match flags.channel {
+        None | Some(Channel::Nightly) => {}
+        Some(_) => return Ok("error: Miri is only available on nightly channel".to_string()),
+    }
+    let (code, source_map) = generate_code(code, &flags, CrateType::Bin, None).await;
+    let req = MiriRequest {
+        edition: flags.edition.unwrap_or_default(),
+        code,
+    };
+    const URL: &str = "https://play.rust-lang.org/miri";
+    let resp = post_json(client, URL, &req).await?;
+    Ok(generate_miri_result_from_response(
+        resp,
+        &source_map,
+        limits,
+    ))
+}
+
+async fn format_code(
+    client: &Client,
+    code: &str,
+    flags: Flags,
+    limits: Limits,
+) -> Result<String, reqwest::Error> {
+    // Statements cannot be formatted on their own, so put them into a `main`,
+    // which is stripped from the result afterwards.
+    let wrap = !flags.bare && parse_code(code, |parsed| parsed.find_main().is_none()).await;
+    let code = if wrap {
+        let (header, body) = extract_code_headers(code);
+        format!("{header}\nfn main() {{\n{body}\n}}\n")
+    } else {
+        code.to_string()
     };
+    let req = FormatRequest {
+        edition: flags.edition.unwrap_or_default(),
+        code,
+    };
+    const URL: &str = "https://play.rust-lang.org/format";
+    let resp = post_json(client, URL, &req).await?;
+    let channel = flags.channel.unwrap_or(Channel::Stable);
+    Ok(generate_format_result_from_response(resp, wrap, channel, limits).await)
+}
+
+async fn generate_format_result_from_response(
+    resp: FormatResponse,
+    wrapped: bool,
+    channel: Channel,
+    limits: Limits,
+) -> String {
+    if !resp.success {
+        let resp = Response {
+            stderr: resp.stderr,
+            stdout: String::new(),
+            success: false,
+        };
+        return generate_result_from_response(resp, channel, limits);
+    }
+    let code = if wrapped {
+        unwrap_code(resp.code).await
+    } else {
+        resp.code
+    };
+    format!("<pre>{}</pre>", encode_minimal(code.trim_end()))
+}
+
+/// Unwrap `main` generated for the code on the parser thread, see `unwrap_main`.
+async fn unwrap_code(code: String) -> String {
+    syntax::parse(code, 0, |parsed| {
+        unwrap_main(parsed).unwrap_or_else(|| parsed.code().to_string())
+    })
+    .await
+}
+
+/// Extract the content of `main` generated by `format_code` from the formatted code.
+fn unwrap_main(parsed: &Parsed<'_>) -> Option<String> {
+    let code = parsed.code();
+    let (header, rest) = code.split_once("fn main() {\n")?;
+    let body = rest.trim_end().strip_suffix('}')?;
+    // Lines inside literals must not be dedented, otherwise their values change.
+    let in_literal = parsed.literal_continuation_lines();
+    let first_line = header.matches('\n').count() + 1;
+    let mut result = String::new();
+    let header = header.trim_end();
+    if !header.is_empty() {
+        result.push_str(header);
+        result.push('\n');
+    }
+    for (index, line) in body.lines().enumerate() {
+        if in_literal.contains(&(first_line + index)) {
+            result.push_str(line);
+        } else {
+            result.push_str(line.strip_prefix("    ").unwrap_or(line));
+        }
+        result.push('\n');
+    }
+    Some(result)
+}
+
+async fn emit_code(
+    client: &Client,
+    code: &str,
+    flags: Flags,
+    target: Target,
+    limits: Limits,
+) -> Result<String, reqwest::Error> {
+    let channel = match (target, flags.channel) {
+        (Target::Hir, None | Some(Channel::Nightly)) => Channel::Nightly,
+        (Target::Hir, Some(_)) => {
+            return Ok("error: HIR is only available on nightly channel".to_string());
+        }
+        (_, channel) => channel.unwrap_or(Channel::Stable),
+    };
+    let generate_flags = flags.clone();
+    let (crate_type, code) = parse_code(code, move |parsed| {
+        let flags = &generate_flags;
+        // Compile as a library unless there is `main`, so that public functions
+        // are kept in the output without being called.
+        let crate_type = if parsed.find_main().is_some() {
+            CrateType::Bin
+        } else {
+            CrateType::Lib
+        };
+        let (code, _) = match (crate_type, target) {
+            // Private functions of a library are left out of the generated code unless used,
+            // so they are made public to show up in the output.
+            (CrateType::Lib, Target::Asm | Target::LlvmIr) => {
+                let code = make_fns_public(parsed);
+                let (header, _) = extract_code_headers(&code);
+                // Code of a library is not parsed again, since it has no `main` to find.
+                let parsed = parsed.parse_other(&code, header.len());
+                generate_code_with_stdin(&parsed, flags, crate_type, None)
+            }
+            _ => generate_code_with_stdin(parsed, flags, crate_type, None),
+        };
+        (crate_type, code)
+    })
+    .await;
+    let req = CompileRequest {
+        target,
+        assembly_flavor: "intel",
+        demangle_assembly: "demangle",
+        process_assembly: "filter",
+        channel,
+        edition: flags.edition.unwrap_or_default(),
+        mode: flags.mode.unwrap_or(Mode::Debug),
+        crate_type,
+        tests: false,
+        backtrace: false,
+        code,
+    };
+    const URL: &str = "https://play.rust-lang.org/compile";
+    let resp = post_json(client, URL, &req).await?;
+    // The prelude is only added to code which isn't bare and has no `main`.
+    let template =
+        (!flags.bare && crate_type == CrateType::Lib).then(|| HEADER.to_string() + PRELUDE);
+    Ok(generate_compile_result_from_response(
+        resp,
+        target,
+        template.as_deref(),
+        channel,
+        limits,
+    ))
+}
+
+/// Insert `pub` before functions at the top level which aren't public.
+fn make_fns_public(parsed: &Parsed<'_>) -> String {
+    let code = parsed.code();
+    let mut result = String::with_capacity(code.len());
+    let mut pos = 0;
+    for start in parsed.private_fns() {
+        result.push_str(&code[pos..start]);
+        result.push_str("pub ");
+        pos = start;
+    }
+    result.push_str(&code[pos..]);
+    result
+}
+
+fn generate_compile_result_from_response(
+    resp: CompileResponse,
+    target: Target,
+    template: Option<&str>,
+    channel: Channel,
+    limits: Limits,
+) -> String {
+    if !resp.success {
+        let resp = Response {
+            stderr: resp.stderr,
+            stdout: resp.stdout,
+            success: false,
+        };
+        return generate_result_from_response(resp, channel, limits);
+    }
+    let output = emit::filter_output(&resp.code, target, template);
+    let output = output.trim();
+    let output = if limits.full {
+        output.into()
+    } else {
+        let max_lines = limits.max_lines(10);
+        utils::truncate_output(output, max_lines, max_lines * 72)
+    };
+    if output.is_empty() {
+        return "(no output)".to_string();
+    }
+    format!("<pre>{}</pre>", encode_minimal(&output))
+}
+
+async fn expand_code(
+    client: &Client,
+    code: &str,
+    flags: Flags,
+    limits: Limits,
+) -> Result<String, reqwest::Error> {
+    match flags.channel {
+        None | Some(Channel::Nightly) => {}
+        Some(_) => {
+            return Ok("error: macro expansion is only available on nightly channel".to_string())
+        }
+    }
+    // Unlike `generate_code_with_stdin`, the code isn't wrapped for printing,
+    // so that the expansion of the `main` generated here contains only
+    // the user's code.
+    let wrap = !flags.bare && parse_code(code, |parsed| parsed.find_main().is_none()).await;
+    let code = if wrap {
+        let (header, body) = extract_code_headers(code);
+        format!("{header}\n{PRELUDE}\nfn main() {{\n{body}\n}}\n")
+    } else {
+        code.to_string()
+    };
+    let req = MacroExpansionRequest {
+        edition: flags.edition.unwrap_or_default(),
+        code,
+    };
+    const URL: &str = "https://play.rust-lang.org/macro-expansion";
+    let resp = post_json(client, URL, &req).await?;
+    Ok(generate_expansion_result_from_response(resp, wrap, limits).await)
+}
+
+async fn generate_expansion_result_from_response(
+    resp: Response,
+    wrapped: bool,
+    limits: Limits,
+) -> String {
+    let channel = Channel::Nightly;
+    if !resp.success {
+        return generate_result_from_response(resp, channel, limits);
+    }
+    let output = emit::strip_prelude(&resp.stdout, wrapped.then_some(PRELUDE));
+    let output = if wrapped {
+        unwrap_code(output).await
+    } else {
+        output
+    };
+    let output = output.trim();
+    let output = if limits.full {
+        output.into()
+    } else {
+        let max_lines = limits.max_lines(10);
+        utils::truncate_output(output, max_lines, max_lines * 72)
+    };
+    if output.is_empty() {
+        return "(no output)".to_string();
+    }
+    format!("<pre>{}</pre>", encode_minimal(&output))
+}
+
+fn generate_miri_result_from_response(
+    mut resp: Response,
+    source_map: &SourceMap,
+    limits: Limits,
+) -> String {
+    let channel = Channel::Nightly;
+    if resp.success {
+        return generate_result_from_response(resp, channel, limits);
+    }
+    let diagnostics = diagnostic::parse(&resp.stderr);
+    let diagnostic = match diagnostics.iter().find(|d| d.level == Level::Error) {
+        Some(diagnostic) => diagnostic,
+        // Not an error from Miri or the compiler, probably a panic.
+        None => {
+            resp.stderr = source_map.rewrite_locations(&resp.stderr).into_owned();
+            return generate_result_from_response(resp, channel, limits);
+        }
+    };
+    let mut result = String::new();
+    render_diagnostic(&mut result, diagnostic, channel);
+    if let Some(location) = diagnostic.location.and_then(|l| source_map.map(l)) {
+        write!(result, " (at {}:{})", location.line, location.column).unwrap();
+    }
+    // Skip the generic help messages of undefined behavior.
+    const GENERIC_HELPS: &[&str] = &["this indicates a bug in the program", "see https://"];
+    let help = diagnostic
+        .helps
+        .iter()
+        .find(|help| !GENERIC_HELPS.iter().any(|prefix| help.starts_with(prefix)))
+        .or_else(|| diagnostic.helps.first());
+    if let Some(help) = help {
+        result.push_str("\nhelp: ");
+        utils::encode_with_code(&mut result, help);
+    }
+    result
+}
+
+fn generate_clippy_result_from_response(
+    resp: Response,
+    channel: Channel,
+    limits: Limits,
+) -> String {
+    let diagnostics = diagnostic::parse(&resp.stderr);
+    if diagnostics.is_empty() {
+        return if resp.success {
+            "(no lints)".to_string()
+        } else {
+            generate_result_from_response(resp, channel, limits)
+        };
+    }
+    const MAX_DIAGNOSTICS: usize = 3;
+    let shown = if limits.full {
+        diagnostics.len()
+    } else {
+        diagnostics.len().min(MAX_DIAGNOSTICS)
+    };
+    let mut result = String::new();
+    for diagnostic in &diagnostics[..shown] {
+        render_diagnostic(&mut result, diagnostic, channel);
+        result.push('\n');
+    }
+    if shown < diagnostics.len() {
+        writeln!(result, "(and {} more)", diagnostics.len() - shown).unwrap();
+    }
+    result
+}
+
+fn generate_check_result_from_response(
+    resp: CompileResponse,
+    source_map: &SourceMap,
+    channel: Channel,
+    limits: Limits,
+) -> String {
+    let diagnostics = diagnostic::parse(&resp.stderr);
+    let count = |level| diagnostics.iter().filter(|d| d.level == level).count();
+    let (errors, warnings) = (count(Level::Error), count(Level::Warning));
+    if diagnostics.is_empty() {
+        return if resp.success {
+            "(no errors or warnings)".to_string()
+        } else {
+            let resp = Response {
+                stderr: resp.stderr,
+                stdout: resp.stdout,
+                success: false,
+            };
+            generate_result_from_response(resp, channel, limits)
+        };
+    }
+    let plural = |n| if n == 1 { "" } else { "s" };
+    let mut result = format!(
+        "{errors} error{}, {warnings} warning{}\n",
+        plural(errors),
+        plural(warnings),
+    );
+    const MAX_DIAGNOSTICS: usize = 5;
+    let shown = if limits.full {
+        diagnostics.len()
+    } else {
+        diagnostics.len().min(MAX_DIAGNOSTICS)
+    };
+    for diagnostic in &diagnostics[..shown] {
+        if let Some(location) = diagnostic.location.and_then(|l| source_map.map(l)) {
+            write!(result, "{}:{}: ", location.line, location.column).unwrap();
+        }
+        render_diagnostic(&mut result, diagnostic, channel);
+        result.push('\n');
+    }
+    if shown < diagnostics.len() {
+        writeln!(result, "(and {} more)", diagnostics.len() - shown).unwrap();
+    }
+    result
+}
+
+fn render_diagnostic(output: &mut String, diagnostic: &Diagnostic<'_>, channel: Channel) {
+    output.push_str(match diagnostic.level {
+        Level::Error => "error",
+        Level::Warning => "warning",
+    });
+    if let Some(code) = diagnostic.code {
+        let url = error_index_url(channel, code);
+        write!(
+            output,
+            r#"[<a href="{}">{}</a>]"#,
+            encode_attribute(&url),
+            code
+        )
+        .unwrap();
+    } else if let Some(lint) = diagnostic.lint {
+        match diagnostic::clippy_lint_url(lint) {
+            Some(url) => write!(
+                output,
+                r#"[<a href="{}">{}</a>]"#,
+                encode_attribute(&url),
+                lint
+            ),
+            None => write!(output, "[{lint}]"),
+        }
+        .unwrap();
+    }
+    output.push_str(": ");
+    utils::encode_with_code(output, diagnostic.message);
+}
+
+fn error_index_url(channel: Channel, code: &str) -> String {
     format!(
-        template! {
-            "#![allow(dead_code)]",
-            "#![allow(unused_imports)]",
-            "{header}",
-            "{prelude}",
-            "fn main() -> Result<(), Box<dyn std::error::Error>> {{",
-            "    {code}",
-            "    Ok(())",
-            "}}",
-        },
-        header = header,
-        prelude = PRELUDE,
-        code = code,
+        "https://doc.rust-lang.org/{}/error-index.html#{}",
+        channel.as_str(),
+        code,
     )
 }
 
-fn generate_result_from_response(resp: Response, channel: Channel, is_private: bool) -> String {
+fn generate_result_from_response(resp: Response, channel: Channel, limits: Limits) -> String {
     if resp.success {
         let output = resp.stdout.trim();
-        let output = if is_private {
+        let output = if limits.full {
             output.into()
         } else {
-            const MAX_LINES: usize = 3;
-            const MAX_TOTAL_COLUMNS: usize = MAX_LINES * 72;
-            utils::truncate_output(output, MAX_LINES, MAX_TOTAL_COLUMNS)
+            let max_lines = limits.max_lines(3);
+            utils::truncate_output(output, max_lines, max_lines * 72)
         };
         if output.is_empty() {
             return "(no output)".to_string();
@@ -153,11 +1169,7 @@ fn generate_result_from_response(resp: Response, channel: Channel, is_private: b
         let line = encode_minimal(line);
         let line = RE_ERROR.replacen(&line, 1, |captures: &Captures<'_>| {
             let err_num = captures.get(1).unwrap().as_str();
-            let url = format!(
-                "https://doc.rust-lang.org/{}/error-index.html#{}",
-                channel.as_str(),
-                err_num,
-            );
+            let url = error_index_url(channel, err_num);
             format!(
                 r#"error<a href="{}">[{}]</a>:"#,
                 encode_attribute(&url),
@@ -182,7 +1194,7 @@ fn generate_result_from_response(resp: Response, channel: Channel, is_private: b
 #[serde(rename_all = "camelCase")]
 struct Request {
     channel: Channel,
-    edition: &'static str,
+    edition: Edition,
     mode: Mode,
     crate_type: CrateType,
     tests: bool,
@@ -191,9 +1203,68 @@ struct Request {
 }
 
 #[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ClippyRequest {
+    channel: Channel,
+    edition: Edition,
+    crate_type: CrateType,
+    code: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CompileRequest {
+    target: Target,
+    assembly_flavor: &'static str,
+    demangle_assembly: &'static str,
+    process_assembly: &'static str,
+    channel: Channel,
+    edition: Edition,
+    mode: Mode,
+    crate_type: CrateType,
+    tests: bool,
+    backtrace: bool,
+    code: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompileResponse {
+    success: bool,
+    code: String,
+    stdout: String,
+    stderr: String,
+}
+
+#[derive(Debug, Serialize)]
+struct FormatRequest {
+    edition: Edition,
+    code: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct FormatResponse {
+    success: bool,
+    code: String,
+    stderr: String,
+}
+
+#[derive(Debug, Serialize)]
+struct MacroExpansionRequest {
+    edition: Edition,
+    code: String,
+}
+
+#[derive(Debug, Serialize)]
+struct MiriRequest {
+    edition: Edition,
+    code: String,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
 #[serde(rename_all = "lowercase")]
 enum CrateType {
     Bin,
+    Lib,
 }
 
 #[derive(Debug, Deserialize)]
@@ -244,6 +1315,297 @@ fn extract_code_headers(code: &str) -> (&str, &str) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use futures::executor::block_on;
+
+    fn generate(
+        code: &str,
+        flags: &Flags,
+        crate_type: CrateType,
+        stdin: Option<&str>,
+    ) -> (String, SourceMap) {
+        block_on(generate_code(code, flags, crate_type, stdin))
+    }
+
+    fn syntax_parse<T: Send + 'static>(
+        code: &str,
+        f: impl FnOnce(&Parsed<'_>) -> T + Send + 'static,
+    ) -> T {
+        block_on(syntax::parse(code.to_string(), 0, f))
+    }
+
+    const SHORT: Limits = Limits {
+        full: false,
+        max_lines: None,
+    };
+    const FULL: Limits = Limits {
+        full: true,
+        max_lines: None,
+    };
+
+    #[test]
+    fn test_generate_clippy_result() {
+        let stderr = "    Checking playground v0.0.1 (/playground)
+warning: redundant clone
+ --> src/main.rs:4:14
+  |
+  = note: `#[warn(clippy::redundant_clone)]` on by default
+
+warning: `playground` (bin \"playground\") generated 1 warning
+";
+        let resp = |stderr: &str| Response {
+            stderr: stderr.to_string(),
+            stdout: String::new(),
+            success: true,
+        };
+        let url = "https://rust-lang.github.io/rust-clippy/master/index.html#redundant_clone";
+        assert_eq!(
+            generate_clippy_result_from_response(resp(stderr), Channel::Stable, SHORT),
+            format!(
+                "warning[<a href=\"{}\">clippy::redundant_clone</a>]: redundant clone\n",
+                encode_attribute(url),
+            ),
+        );
+        assert_eq!(
+            generate_clippy_result_from_response(resp(""), Channel::Stable, SHORT),
+            "(no lints)",
+        );
+    }
+
+    #[test]
+    fn test_generate_check_result() {
+        let (code, source_map) = generate("let x = 1;\ny", &Flags::default(), CrateType::Bin, None);
+        // Find the line and column of the given line of code in the generated code.
+        let find = |line: &str| {
+            let (number, l) = code
+                .lines()
+                .enumerate()
+                .find(|(_, l)| l.trim_start() == line)
+                .unwrap();
+            (number + 1, l.len() - line.len() + 1)
+        };
+        let (x_line, x_column) = find("let x = 1;");
+        let (y_line, y_column) = find("y");
+        let stderr = format!(
+            "warning: unused variable: `x`
+ --> src/main.rs:{}:{}
+  |
+  = note: `#[warn(unused_variables)]` on by default
+
+error[E0425]: cannot find value `y` in this scope
+ --> src/main.rs:{}:{}
+
+warning: unused result
+ --> src/main.rs:1:1
+
+error: could not compile `playground` (bin \"playground\") due to previous error; 2 warnings emitted
+",
+            x_line,
+            x_column + 4,
+            y_line,
+            y_column,
+        );
+        let resp = CompileResponse {
+            success: false,
+            code: String::new(),
+            stdout: String::new(),
+            stderr,
+        };
+        assert_eq!(
+            generate_check_result_from_response(resp, &source_map, Channel::Stable, SHORT),
+            format!(
+                "1 error, 2 warnings\n\
+                 1:5: warning[unused_variables]: unused variable: <code>x</code>\n\
+                 2:1: error[<a href=\"{}\">E0425</a>]: cannot find value <code>y</code> in this scope\n\
+                 warning: unused result\n",
+                encode_attribute(&error_index_url(Channel::Stable, "E0425")),
+            ),
+        );
+    }
+
+    #[test]
+    fn test_unwrap_main() {
+        let unwrap = |code: &str| syntax_parse(code, unwrap_main);
+        let code = "#![feature(test)]\n\nfn main() {\n    let a = vec![1, 2];\n    \
+                    if a.is_empty() {\n        1\n    } else {\n        2\n    }\n}\n";
+        assert_eq!(
+            unwrap(code).as_deref(),
+            Some(
+                "#![feature(test)]\nlet a = vec![1, 2];\n\
+                 if a.is_empty() {\n    1\n} else {\n    2\n}\n"
+            ),
+        );
+        assert_eq!(unwrap("fn main() {}\n"), None);
+        let code = "fn main() {\n    let s = \"a\n    b\";\n    let t = r\"\n\n  c\";\n}\n";
+        assert_eq!(
+            unwrap(code).as_deref(),
+            Some("let s = \"a\n    b\";\nlet t = r\"\n\n  c\";\n"),
+        );
+    }
+
+    #[test]
+    fn test_generate_expansion_result() {
+        let stdout = "\
+#![feature(prelude_import)]
+#[prelude_import]
+use std::prelude::rust_2021::*;
+#[macro_use]
+extern crate std;
+extern crate lazy_static;
+use lazy_static::lazy_static;
+use std::any::{type_name, Any};
+fn type_name_of_val<T: ?Sized>(_: &T) -> &'static str {
+    type_name::<T>()
+}
+fn main() {
+    let v =
+        <[_]>::into_vec(#[rustc_box] ::alloc::boxed::Box::new([1, 2]));
+}
+";
+        let resp = Response {
+            stderr: String::new(),
+            stdout: stdout.to_string(),
+            success: true,
+        };
+        assert_eq!(
+            block_on(generate_expansion_result_from_response(resp, true, FULL)),
+            "<pre>let v =\n    &lt;[_]&gt;::into_vec(#[rustc_box] \
+             ::alloc::boxed::Box::new([1, 2]));</pre>",
+        );
+    }
+
+    #[test]
+    fn test_generate_miri_result() {
+        let stderr = "\
+error: Undefined Behavior: dereferencing pointer failed: null pointer is a dangling pointer
+ --> src/main.rs:3:14
+  |
+3 |     unsafe { *p }
+  |              ^^ dereferencing pointer failed
+  |
+  = help: this indicates a bug in the program: it performed an invalid operation, and caused Undefined Behavior
+  = help: see https://doc.rust-lang.org/nightly/reference/behavior-considered-undefined.html for further information
+  = note: BACKTRACE:
+  = note: inside `main` at src/main.rs:3:14: 3:16
+
+error: aborting due to previous error
+";
+        let resp = Response {
+            stderr: stderr.to_string(),
+            stdout: String::new(),
+            success: false,
+        };
+        let code = "#![feature(strict_provenance)]\n\
+                    let p = std::ptr::null::<i32>();\n\
+                    unsafe { *p }";
+        let (generated, source_map) = generate(code, &Flags::default(), CrateType::Bin, None);
+        let (line, l) = generated
+            .lines()
+            .enumerate()
+            .find(|(_, l)| l.ends_with("unsafe { *p }"))
+            .unwrap();
+        let column = l.find("*p").unwrap() + 1;
+        let stderr = stderr.replace("3:14", &format!("{}:{}", line + 1, column));
+        let resp = Response { stderr, ..resp };
+        assert_eq!(
+            generate_miri_result_from_response(resp, &source_map, SHORT),
+            "error: Undefined Behavior: dereferencing pointer failed: \
+             null pointer is a dangling pointer (at 3:10)\n\
+             help: this indicates a bug in the program: \
+             it performed an invalid operation, and caused Undefined Behavior",
+        );
+    }
+
+    #[test]
+    fn test_generate_test_result() {
+        let stdout = "\n\
+running 2 tests
+test a ... ok
+test b ... FAILED
+
+failures:
+
+---- b stdout ----
+thread 'b' panicked at src/lib.rs:5:5:
+assertion failed: 1 > 2
+
+failures:
+    b
+
+test result: FAILED. 1 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
+";
+        let resp = || Response {
+            stderr: String::new(),
+            stdout: stdout.to_string(),
+            success: false,
+        };
+        assert_eq!(
+            generate_test_result_from_response(resp(), Channel::Stable, SHORT),
+            "1 passed, 1 failed: <code>b</code> panicked at \
+             src/lib.rs:5:5: assertion failed: 1 &gt; 2",
+        );
+        let private = generate_test_result_from_response(resp(), Channel::Stable, FULL);
+        assert!(private.starts_with("1 passed, 1 failed\n<pre>running 2 tests\n"));
+    }
+
+    #[test]
+    fn test_check_edition() {
+        let version = |version: &str| Version {
+            date: "2025-02-17".to_string(),
+            hash: "4d91de4e48198da2e33413efdcd9cd2cc0c46688".to_string(),
+            version: version.to_string(),
+        };
+        assert_eq!(
+            check_edition(&version("1.85.0"), Channel::Stable, Edition::E2024),
+            Ok(()),
+        );
+        assert_eq!(
+            check_edition(&version("1.86.0-nightly"), Channel::Nightly, Edition::E2024),
+            Ok(()),
+        );
+        assert_eq!(
+            check_edition(&version("1.84.1"), Channel::Stable, Edition::E2024),
+            Err("error: edition 2024 is not supported by rustc 1.84.1 on stable channel".into()),
+        );
+        assert_eq!(
+            check_edition(&version("unknown"), Channel::Beta, Edition::E2024),
+            Ok(()),
+        );
+    }
+
+    #[test]
+    fn test_check_channel() {
+        let resp = r#"{
+            "stable": {
+                "rustc": {"version": "1.84.1", "hash": "e71f9a9a98b", "date": "2025-01-27"},
+                "rustfmt": {"version": "1.8.0-stable", "hash": "e71f9a9a98b", "date": "2025-01-27"},
+                "miri": null
+            },
+            "nightly": {
+                "rustc": {"version": "1.86.0-nightly", "hash": "ad211ced81", "date": "2025-01-29"}
+            },
+            "future": {
+                "rustc": {"version": "2.0.0", "hash": "0000000000", "date": "2030-01-01"}
+            }
+        }"#;
+        let versions = parse_versions(serde_json::from_str(resp).unwrap());
+        assert_eq!(versions.len(), 2);
+        let version = |channel, edition| {
+            check_channel(&versions, channel, edition).map(|v| v.version.as_str())
+        };
+        assert_eq!(version(Channel::Stable, Edition::E2021), Ok("1.84.1"));
+        assert_eq!(
+            version(Channel::Stable, Edition::E2024),
+            Err("error: edition 2024 is not supported by rustc 1.84.1 on stable channel".into()),
+        );
+        assert_eq!(
+            version(Channel::Nightly, Edition::E2024),
+            Ok("1.86.0-nightly")
+        );
+        assert_eq!(
+            version(Channel::Beta, Edition::E2021),
+            Err("error: beta channel is not available on the playground".into()),
+        );
+    }
 
     #[test]
     fn test_extract_code_headers() {
@@ -271,4 +1633,202 @@ mod tests {
             assert_eq!(result, (header, body));
         }
     }
+
+    #[test]
+    fn test_group_channel_results() {
+        let error = |channel: &str| {
+            format!(
+                "error<a href=\"https://doc.rust-lang.org/{channel}/error-index.html#E0425\">\
+                 [E0425]</a>: cannot find value"
+            )
+        };
+        let results = [
+            (Channel::Stable, Some("1.84.1"), "<pre>1</pre>".to_string()),
+            (Channel::Beta, Some("1.85.0-beta.7"), error("beta")),
+            (Channel::Nightly, Some("1.86.0-nightly"), error("nightly")),
+        ];
+        let results = results
+            .into_iter()
+            .map(|(channel, version, result)| (channel, version.map(str::to_string), result));
+        assert_eq!(
+            group_channel_results(results),
+            format!(
+                "<b>stable (1.84.1)</b>: <pre>1</pre>\n\
+                 <b>beta (1.85.0-beta.7), nightly (1.86.0-nightly)</b>: {}",
+                error("beta"),
+            ),
+        );
+
+        // Channels failing to run are shown along with the others.
+        let results = [
+            (Channel::Stable, Some("1.84.1"), "<pre>1</pre>"),
+            (Channel::Beta, Some("1.85.0-beta.7"), "<pre>1</pre>"),
+            (Channel::Nightly, None, "error: timeout"),
+        ];
+        let results = results.into_iter().map(|(channel, version, result)| {
+            (channel, version.map(str::to_string), result.to_string())
+        });
+        assert_eq!(
+            group_channel_results(results),
+            "<b>stable (1.84.1), beta (1.85.0-beta.7)</b>: <pre>1</pre>\n\
+             <b>nightly</b>: error: timeout",
+        );
+    }
+
+    #[test]
+    fn test_make_fns_public() {
+        let make_public = |code: &str| syntax_parse(code, make_fns_public);
+        assert_eq!(
+            make_public("/// Square.\nfn square(x: i32) -> i32 { x * x }\npub fn f() {}"),
+            "/// Square.\npub fn square(x: i32) -> i32 { x * x }\npub fn f() {}",
+        );
+        assert_eq!(make_public("fn f() {"), "fn f() {");
+    }
+
+    #[test]
+    fn test_generate_size_code() {
+        let code = block_on(size_code("u8, HashMap<u8, u8>", true)).unwrap();
+        assert!(code.starts_with(
+            "let rows = [\n    \
+             (\"u8\", size_of::<u8>(), align_of::<u8>(), \
+             size_of::<Option<u8>>() == size_of::<u8>()),\n    \
+             (\"HashMap<u8, u8>\", size_of::<HashMap<u8, u8>>(), "
+        ));
+        assert!(code.contains("let niche = true;"));
+        assert_eq!(block_on(size_code("1 + 1", false)), None);
+    }
+
+    #[test]
+    fn test_generate_code_with_stdin() {
+        let bare = Flags {
+            bare: true,
+            ..Flags::default()
+        };
+        let (code, source_map) =
+            generate("fn main() { x }", &bare, CrateType::Bin, Some("a\n\"b\""));
+        assert!(code.starts_with("fn main() {__eval_feed_stdin(\"a\\n\\\"b\\\"\"); x }\n"));
+        assert!(code.contains("fn __eval_feed_stdin("));
+        let column = code.find(" x }").unwrap() + 2;
+        let location = diagnostic::Location { line: 1, column };
+        assert_eq!(source_map.map(location).map(|l| l.column), Some(13));
+
+        let (code, _) = generate("1 + 1", &Flags::default(), CrateType::Bin, Some("a"));
+        assert!(code.contains("{\n    __eval_feed_stdin(\"a\");\n"));
+        assert!(code.contains("fn __eval_feed_stdin("));
+
+        let (code, _) = generate("fn f() {}", &bare, CrateType::Bin, Some("a"));
+        assert_eq!(code, "fn f() {}");
+    }
+
+    #[test]
+    fn test_check_stdin() {
+        let check_stdin = |code: &str, flags: &Flags| {
+            let flags = flags.clone();
+            block_on(parse_code(code, move |parsed| check_stdin(parsed, &flags)))
+        };
+        assert_eq!(check_stdin("1 + 1", &Flags::default()), None);
+        let bare = Flags {
+            bare: true,
+            ..Flags::default()
+        };
+        assert_eq!(check_stdin("fn main() {}", &bare), None);
+        assert!(check_stdin("fn f() {}", &bare).is_some());
+        let test = Flags {
+            task: Some(Task::Test),
+            ..Flags::default()
+        };
+        assert!(check_stdin("#[test]\nfn f() {}", &test).is_some());
+    }
+
+    #[test]
+    fn test_generate_code_to_send() {
+        let code = "struct A;\nlet a = A;\nfn f() {}\nprintln!(\"{}\", 1);\n[1, 2] // done";
+        let (generated, _) = generate(code, &Flags::default(), CrateType::Bin, None);
+        let (module, main) = generated.split_once("\nfn main() ").unwrap();
+        assert!(module.ends_with("\nstruct A;\nfn f() {}"));
+        assert!(main.contains("\nlet a = A;\n"));
+        assert!(main.contains("println!(\"{}\", 1);\n"));
+        assert!(main.contains(
+            "match {\n        [1, 2]\n    } {\n        \
+             __value => if let Some(__value) = (&(&__value,)).__eval_printable() {\n            \
+             println!(\"{:?}\", __value);\n        \
+             },\n    }\n     // done"
+        ));
+
+        let flags = Flags {
+            pretty: true,
+            show_type: true,
+            ..Flags::default()
+        };
+        let (generated, _) = generate("[1, 2]", &flags, CrateType::Bin, None);
+        assert!(generated.contains(
+            "{\n            \
+             println!(\"{:#?}: {}\", __value, type_name_of_val(__value));\n        }"
+        ));
+
+        let (generated, _) = generate(
+            "f().await;\nasync { 1 }",
+            &Flags::default(),
+            CrateType::Bin,
+            None,
+        );
+        assert!(generated.contains("\n#[tokio::main]\nasync fn main() -> "));
+        assert!(generated.contains("{\n        async { 1 }\n    }.await {"));
+
+        // Code with `main` is sent as is.
+        for code in ["fn main () {}", "async fn main() {}"] {
+            let (generated, _) = generate(code, &Flags::default(), CrateType::Bin, None);
+            assert_eq!(generated, code);
+        }
+    }
+
+    /// Compile the generated code with the local compiler, and run it to get its output.
+    fn run_generated(name: &str, code: &str) -> String {
+        use std::process::Command;
+        // Crates provided by the playground aren't available here.
+        let code: String = code
+            .lines()
+            .filter(|line| !line.contains("lazy_static") && !line.contains("once_cell"))
+            .map(|line| format!("{line}\n"))
+            .collect();
+        let dir = std::env::temp_dir().join(format!("rustevalbot-{}-{name}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("main.rs");
+        let binary = dir.join("main");
+        std::fs::write(&source, code).unwrap();
+        let output = Command::new("rustc")
+            .args(["--edition", "2021", "-o"])
+            .args([&binary, &source])
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+        assert!(output.status.success(), "failed to compile: {stderr}");
+        let output = Command::new(&binary).output().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[test]
+    fn test_run_generated_code() {
+        let code = "fn f() {}\nf()";
+        let display = Flags {
+            display: true,
+            ..Flags::default()
+        };
+        let (generated, _) = generate(code, &display, CrateType::Bin, None);
+        assert_eq!(run_generated("display", &generated), "");
+        let show_type = Flags {
+            show_type: true,
+            ..Flags::default()
+        };
+        let (generated, _) = generate(
+            "let mut v = vec![1];\nv.push(2)",
+            &show_type,
+            CrateType::Bin,
+            None,
+        );
+        assert_eq!(run_generated("unit_type", &generated), "");
+        let (generated, _) = generate("&()", &show_type, CrateType::Bin, None);
+        assert_eq!(run_generated("show_type", &generated), "(): &()\n");
+    }
 }
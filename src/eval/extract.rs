@@ -0,0 +1,272 @@
+use crate::utils;
+use telegram_types::bot::types::{MessageEntity, MessageEntityKind};
+
+/// Code extracted from the content of a message.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Code<'a> {
+    pub text: &'a str,
+    /// Input to feed into stdin of the program.
+    pub stdin: Option<&'a str>,
+    /// Whether the code is marked as code by the client,
+    /// so that its characters are not mangled.
+    pub verbatim: bool,
+}
+
+/// Languages of fenced blocks which are taken as Rust code.
+const RUST_LANGUAGES: &[&str] = &["", "rust", "rs"];
+
+/// Extract the code from the content, which is a part of the text of a message
+/// with the given entities.
+///
+/// The code is the first code block in the content,
+/// either marked by the entities or fenced with backticks,
+/// or the content itself if there is none.
+/// The input for stdin is the code block following it,
+/// or the text after a line of `--stdin`.
+pub fn extract_code<'a>(text: &'a str, entities: &[MessageEntity], content: &'a str) -> Code<'a> {
+    // Entities are only usable if the content is really a part of the text.
+    let start = (content.as_ptr() as usize)
+        .checked_sub(text.as_ptr() as usize)
+        .filter(|start| start + content.len() <= text.len());
+    if let Some(start) = start {
+        let range = start..start + content.len();
+        let trimmed = content.trim();
+        let mut blocks = entities.iter().filter_map(|entity| {
+            let code = utils::entity_text(text, entity)?;
+            let start = code.as_ptr() as usize - text.as_ptr() as usize;
+            let in_content = range.start <= start && start + code.len() <= range.end;
+            match entity.kind {
+                MessageEntityKind::Pre if in_content => Some(code),
+                // Inline code is only taken if it covers the whole content.
+                MessageEntityKind::Code if in_content && code.trim() == trimmed => Some(code),
+                _ => None,
+            }
+        });
+        if let Some(code) = blocks.next() {
+            // The input may follow a line of `--stdin` either inside or after the code block.
+            let (code, stdin) = split_stdin(code);
+            let stdin = stdin.or_else(|| blocks.next()).or_else(|| {
+                let end = code.as_ptr() as usize - text.as_ptr() as usize + code.len();
+                split_stdin(&text[end..range.end]).1
+            });
+            return Code {
+                text: code,
+                stdin,
+                verbatim: true,
+            };
+        }
+    }
+    let (code, stdin) = split_stdin(content);
+    let (code, next_block) = strip_fences(code);
+    let stdin = stdin.map(|stdin| match fenced_blocks(stdin).first() {
+        Some((_, stdin)) => stdin,
+        None => stdin,
+    });
+    Code {
+        text: code,
+        stdin: stdin.or(next_block),
+        verbatim: false,
+    }
+}
+
+/// Split the text at the line of `--stdin` into the code and the input.
+fn split_stdin(text: &str) -> (&str, Option<&str>) {
+    let mut pos = 0;
+    for line in text.split_inclusive('\n') {
+        // The dashes may have been converted by the client.
+        if matches!(line.trim(), "--stdin" | "—stdin") {
+            return (&text[..pos], Some(&text[pos + line.len()..]));
+        }
+        pos += line.len();
+    }
+    (text, None)
+}
+
+/// Get the code from fenced blocks in the text, preferring the first block in Rust,
+/// along with the block following it if any.
+fn strip_fences(text: &str) -> (&str, Option<&str>) {
+    let blocks = fenced_blocks(text);
+    let index = blocks
+        .iter()
+        .position(|(language, _)| RUST_LANGUAGES.contains(&language.to_ascii_lowercase().as_str()))
+        .unwrap_or(0);
+    if let Some((_, code)) = blocks.get(index) {
+        return (code, blocks.get(index + 1).map(|(_, block)| *block));
+    }
+    // Inline code written with backticks around the whole text.
+    let trimmed = text.trim();
+    match trimmed.strip_prefix('`').and_then(|s| s.strip_suffix('`')) {
+        Some(code) if !code.contains('`') => (code, None),
+        _ => (text, None),
+    }
+}
+
+/// Find all blocks fenced with "```", returning their languages and content.
+fn fenced_blocks(text: &str) -> Vec<(&str, &str)> {
+    const FENCE: &str = "```";
+    let mut blocks = vec![];
+    let mut rest = text;
+    while let Some(start) = rest.find(FENCE) {
+        let block = &rest[start + FENCE.len()..];
+        let end = match block.find(FENCE) {
+            Some(end) => end,
+            None => break,
+        };
+        rest = &block[end + FENCE.len()..];
+        let block = &block[..end];
+        // The language is the word right after the opening fence on the same line.
+        let (language, code) = match block.split_once('\n') {
+            Some((first, code)) if !first.trim().contains(char::is_whitespace) => {
+                (first.trim(), code)
+            }
+            _ => ("", block),
+        };
+        blocks.push((language, code));
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(kind: MessageEntityKind, offset: i32, length: i32) -> MessageEntity {
+        MessageEntity {
+            kind,
+            offset,
+            length,
+            url: None,
+            user: None,
+        }
+    }
+
+    fn extract<'a>(text: &'a str, entities: &[MessageEntity], content_start: usize) -> Code<'a> {
+        extract_code(text, entities, &text[content_start..])
+    }
+
+    #[test]
+    fn test_extract_entities() {
+        let text = "/eval “a”\nlet a = “b”;";
+        let pre = entity(MessageEntityKind::Pre, 10, 12);
+        assert_eq!(
+            extract(text, &[pre], 6),
+            Code {
+                text: "let a = “b”;",
+                stdin: None,
+                verbatim: true,
+            },
+        );
+        // Inline code needs to cover the whole content.
+        let code = entity(MessageEntityKind::Code, 6, 3);
+        assert_eq!(
+            extract(text, &[code], 6),
+            Code {
+                text: "“a”\nlet a = “b”;",
+                stdin: None,
+                verbatim: false,
+            },
+        );
+        let text = "/eval `1 + 1` ";
+        let code = entity(MessageEntityKind::Code, 6, 7);
+        assert_eq!(
+            extract(text, &[code], 6),
+            Code {
+                text: "`1 + 1`",
+                stdin: None,
+                verbatim: true,
+            },
+        );
+        // Entities outside of the content are ignored.
+        let text = "`x` /eval 1";
+        let code = entity(MessageEntityKind::Pre, 0, 3);
+        assert_eq!(
+            extract(text, &[code], 10),
+            Code {
+                text: "1",
+                stdin: None,
+                verbatim: false,
+            },
+        );
+    }
+
+    #[test]
+    fn test_extract_fenced() {
+        let cases: &[(&str, &str)] = &[
+            ("```rust\nfn main() {}\n```", "fn main() {}\n"),
+            ("```\n1 + 1```", "1 + 1"),
+            ("```1 + 1```", "1 + 1"),
+            ("before\n```text\nhello\n```\n```rs\n2```", "2"),
+            ("```text\nhello\n```", "hello\n"),
+            ("`1 + 1`", "1 + 1"),
+            ("`a` + `b`", "`a` + `b`"),
+            ("```unclosed", "```unclosed"),
+            ("1 + 1", "1 + 1"),
+        ];
+        for &(content, expected) in cases {
+            assert_eq!(
+                extract(content, &[], 0),
+                Code {
+                    text: expected,
+                    stdin: None,
+                    verbatim: false,
+                },
+                "content: {content:?}",
+            );
+        }
+    }
+
+    #[test]
+    fn test_extract_stdin() {
+        let text = "/eval a\nb";
+        let pre = |offset, length| entity(MessageEntityKind::Pre, offset, length);
+        assert_eq!(
+            extract(text, &[pre(6, 1), pre(8, 1)], 6),
+            Code {
+                text: "a",
+                stdin: Some("b"),
+                verbatim: true,
+            },
+        );
+        // The line of `--stdin` can be outside of the code block.
+        let text = "/eval a\n--stdin\nb";
+        assert_eq!(
+            extract(text, &[pre(6, 1)], 6),
+            Code {
+                text: "a",
+                stdin: Some("b"),
+                verbatim: true,
+            },
+        );
+        let text = "/eval a\n--stdin\nb";
+        assert_eq!(
+            extract(text, &[pre(6, 11)], 6),
+            Code {
+                text: "a\n",
+                stdin: Some("b"),
+                verbatim: true,
+            },
+        );
+        let cases: &[(&str, &str, Option<&str>)] = &[
+            (
+                "```rust\n1```\n```\nhello```\n```\nworld```",
+                "1",
+                Some("hello"),
+            ),
+            ("```text\nhello```\n```rust\n1```", "1", None),
+            ("1\n--stdin\nhello\n", "1\n", Some("hello\n")),
+            ("```\n1```\n —stdin \n```\nhello```", "1", Some("hello")),
+            ("1\n--stdin", "1\n", Some("")),
+        ];
+        for &(content, code, stdin) in cases {
+            assert_eq!(
+                extract(content, &[], 0),
+                Code {
+                    text: code,
+                    stdin,
+                    verbatim: false,
+                },
+                "content: {content:?}",
+            );
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Summary of the output of libtest.
+#[derive(Debug, Eq, PartialEq)]
+pub struct TestSummary<'a> {
+    pub passed: usize,
+    pub failed: usize,
+    pub ignored: usize,
+    pub failures: Vec<TestFailure<'a>>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct TestFailure<'a> {
+    pub name: &'a str,
+    /// The panic message, including the location of the panic.
+    pub panic: Option<String>,
+}
+
+/// Parse the output of libtest.
+///
+/// Returns `None` if no test result is found, which usually means
+/// that the tests failed to compile.
+pub fn parse_output(output: &str) -> Option<TestSummary<'_>> {
+    static RE_RESULT: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"(?m)^test result: \w+\. (\d+) passed; (\d+) failed; (\d+) ignored;").unwrap()
+    });
+    static RE_FAILURE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"(?m)^---- (\S+) stdout ----$").unwrap());
+
+    let captures = RE_RESULT.captures(output)?;
+    let count = |i| captures.get(i).unwrap().as_str().parse().unwrap_or(0);
+    let (passed, failed, ignored) = (count(1), count(2), count(3));
+
+    let sections = RE_FAILURE.captures_iter(output).collect::<Vec<_>>();
+    let failures = sections
+        .iter()
+        .enumerate()
+        .map(|(i, captures)| {
+            let start = captures.get(0).unwrap().end();
+            let end = match sections.get(i + 1) {
+                Some(next) => next.get(0).unwrap().start(),
+                None => output[start..]
+                    .find("\nfailures:")
+                    .map_or(output.len(), |pos| start + pos),
+            };
+            TestFailure {
+                name: captures.get(1).unwrap().as_str(),
+                panic: find_panic(&output[start..end]),
+            }
+        })
+        .collect();
+
+    Some(TestSummary {
+        passed,
+        failed,
+        ignored,
+        failures,
+    })
+}
+
+/// Find the panic message in the captured output of a test.
+fn find_panic(output: &str) -> Option<String> {
+    let mut lines = output.lines();
+    let line = lines.find(|line| line.starts_with("thread '"))?;
+    let (_, panic) = line.split_once("' panicked at ")?;
+    // Since Rust 1.73, the message is put in the following line.
+    Some(match panic.strip_suffix(':') {
+        Some(location) => match lines.next() {
+            Some(message) => format!("{location}: {message}"),
+            None => location.to_string(),
+        },
+        None => panic.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_output() {
+        let output = "\n\
+running 4 tests
+test a ... ok
+test b ... ignored
+test tests::overflow ... FAILED
+test tests::old ... FAILED
+
+failures:
+
+---- tests::overflow stdout ----
+thread 'tests::overflow' panicked at src/lib.rs:12:9:
+attempt to add with overflow
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+
+---- tests::old stdout ----
+thread 'tests::old' panicked at 'explicit panic', src/lib.rs:20:9
+
+
+failures:
+    tests::old
+    tests::overflow
+
+test result: FAILED. 1 passed; 2 failed; 1 ignored; 0 measured; 0 filtered out; finished in 0.00s
+";
+        assert_eq!(
+            parse_output(output),
+            Some(TestSummary {
+                passed: 1,
+                failed: 2,
+                ignored: 1,
+                failures: vec![
+                    TestFailure {
+                        name: "tests::overflow",
+                        panic: Some("src/lib.rs:12:9: attempt to add with overflow".to_string()),
+                    },
+                    TestFailure {
+                        name: "tests::old",
+                        panic: Some("'explicit panic', src/lib.rs:20:9".to_string()),
+                    },
+                ],
+            }),
+        );
+    }
+
+    #[test]
+    fn test_parse_output_no_result() {
+        assert_eq!(parse_output(""), None);
+        assert_eq!(
+            parse_output("error[E0425]: cannot find value `x` in this scope"),
+            None,
+        );
+    }
+}
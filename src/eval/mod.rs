@@ -1,18 +1,32 @@
-use self::record::RecordService;
-use crate::bot::Bot;
+use self::config::ChatConfig;
+use self::record::{RecordService, Source};
+use self::share::Share;
+use crate::bot::{Bot, BotRequest};
 use crate::eval::parse::Command;
+use crate::maintenance;
 use crate::utils;
-use futures::future;
+use futures::{future, FutureExt as _};
 use log::{debug, info, warn};
 use reqwest::Client;
 use std::future::Future;
 use std::sync::Arc;
-use telegram_types::bot::types::{Message, UpdateContent, UpdateId};
+use telegram_types::bot::types::{
+    CallbackQuery, ChatId, ChatMemberStatus, Message, MessageEntity, MessageId, UpdateContent,
+    UpdateId,
+};
 use tokio::sync::Mutex;
 
+mod config;
+mod diagnostic;
+mod emit;
 mod execute;
+mod extract;
+mod libtest;
 mod parse;
 mod record;
+mod share;
+mod source_map;
+mod syntax;
 
 /// Eval bot.
 pub struct EvalBot {
@@ -25,6 +39,7 @@ impl EvalBot {
     /// Create new eval bot instance.
     pub fn new(client: Client, bot: Bot) -> Self {
         let records = Mutex::new(RecordService::init());
+        config::init();
         info!("EvalBot authorized as @{}", bot.username);
         EvalBot {
             bot,
@@ -36,22 +51,42 @@ impl EvalBot {
     /// Handle the update.
     pub async fn handle_update(self: Arc<Self>, id: UpdateId, content: UpdateContent) {
         match content {
-            UpdateContent::Message(message) => self.handle_message(id, &message).await,
+            UpdateContent::Message(message) => {
+                let config_command = message
+                    .text
+                    .as_deref()
+                    .and_then(parse::parse_config_command);
+                match config_command {
+                    Some(command) => self.handle_config_command(id, &message, command).await,
+                    None => self.handle_message(id, &message).await,
+                }
+            }
             UpdateContent::EditedMessage(message) => self.handle_edit_message(id, &message).await,
+            UpdateContent::CallbackQuery(query) => self.handle_callback_query(id, &query).await,
             _ => {}
         }
     }
 
     async fn handle_message(&self, id: UpdateId, message: &Message) {
         self.records.lock().await.clear_old_records(&message.date);
-        let reply_future = match self.prepare_command(id, message) {
-            Some(future) => async { generate_reply(future.await) },
+        let Evaluation {
+            future,
+            share,
+            source,
+        } = match self.prepare_command(id, message) {
+            Some(evaluation) => evaluation,
             None => return,
         };
+        let reply_future = async { generate_reply(future.await) };
         let msg_id = message.message_id;
         let date = message.date.clone();
-        self.records.lock().await.push_record(msg_id, date);
         let chat_id = message.chat.id;
+        let has_share = share.is_some();
+        let mut records = self.records.lock().await;
+        records.push_record(chat_id, msg_id, date);
+        records.set_share(chat_id, msg_id, share);
+        records.set_source(chat_id, msg_id, make_source(message, source));
+        drop(records);
 
         // Send the placeholder reply.
         let placeholder_future = async {
@@ -61,7 +96,10 @@ impl EvalBot {
                 Ok(msg) => {
                     let reply_id = msg.message_id;
                     debug!("{}> placeholder sent as {}", id.0, reply_id.0);
-                    self.records.lock().await.set_reply(msg_id, reply_id);
+                    self.records
+                        .lock()
+                        .await
+                        .set_reply(chat_id, msg_id, reply_id);
                     Ok(reply_id)
                 }
                 Err(err) => {
@@ -80,7 +118,7 @@ impl EvalBot {
 
         let reply = reply.trim_matches(char::is_whitespace);
         debug!("{}> updating reply: {:?}", id.0, reply);
-        let request = self.bot.edit_message(chat_id, reply_id, reply);
+        let request = self.edit_reply(chat_id, reply_id, reply, has_share);
         match request.execute().await {
             Ok(_) => debug!("{}> reply sent", id.0),
             Err(err) => warn!("{}> error updating: {:?}", id.0, err),
@@ -88,18 +126,52 @@ impl EvalBot {
     }
 
     async fn handle_edit_message(&self, id: UpdateId, message: &Message) {
-        let msg_id = message.message_id;
-        let reply_id = match self.records.lock().await.find_reply(msg_id) {
-            Some(reply) => reply,
-            None => return,
-        };
+        let (chat_id, msg_id) = (message.chat.id, message.message_id);
+        let records = self.records.lock().await;
+        if let Some(reply_id) = records.find_reply(chat_id, msg_id) {
+            drop(records);
+            let evaluation = self.prepare_command(id, message);
+            let source = evaluation.as_ref().and_then(|e| e.source);
+            let source = make_source(message, source);
+            self.update_reply(id, message, msg_id, reply_id, evaluation, source)
+                .await;
+        } else if let Some((command_id, reply_id, source)) = records.find_by_source(chat_id, msg_id)
+        {
+            // The message which the code of a command comes from is edited.
+            let source = source.clone();
+            drop(records);
+            debug!(
+                "{}> source [{}] of [{}] edited",
+                id.0, msg_id.0, command_id.0
+            );
+            let evaluation = self.prepare_eval(id, message, &source.command, &[], Some(message));
+            let source = evaluation
+                .as_ref()
+                .and_then(|e| e.source)
+                .map(|_| source.clone());
+            self.update_reply(id, message, command_id, reply_id, evaluation, source)
+                .await;
+        }
+    }
+
+    /// Update the existing reply of the command to the result of the new evaluation,
+    /// or delete it if there is nothing to evaluate anymore.
+    async fn update_reply(
+        &self,
+        id: UpdateId,
+        message: &Message,
+        msg_id: MessageId,
+        reply_id: MessageId,
+        evaluation: Option<Evaluation<impl Future<Output = Result<String, reqwest::Error>>>>,
+        source: Option<Source>,
+    ) {
         let chat_id = message.chat.id;
-        let reply_future = match self.prepare_command(id, message) {
-            Some(future) => async { generate_reply(future.await) },
+        let Evaluation { future, share, .. } = match evaluation {
+            Some(evaluation) => evaluation,
             None => {
                 // Delete reply if the new command is invalid.
                 debug!("{}> deleting", id.0);
-                self.records.lock().await.remove_reply(msg_id);
+                self.records.lock().await.remove_reply(chat_id, msg_id);
                 let request = self.bot.delete_message(chat_id, reply_id);
                 match request.execute().await {
                     Ok(_) => debug!("{}> deleted", id.0),
@@ -108,6 +180,13 @@ impl EvalBot {
                 return;
             }
         };
+        let reply_future = async { generate_reply(future.await) };
+
+        let has_share = share.is_some();
+        let mut records = self.records.lock().await;
+        records.set_share(chat_id, msg_id, share);
+        records.set_source(chat_id, msg_id, source);
+        drop(records);
 
         // Update the reply with a placeholder.
         let placeholder_future = async {
@@ -123,18 +202,147 @@ impl EvalBot {
         let (_placeholder, reply) = future::join(placeholder_future, reply_future).await;
         let reply = reply.trim_matches(char::is_whitespace);
         debug!("{}> updating: {:?}", id.0, reply);
-        let request = self.bot.edit_message(chat_id, reply_id, reply);
+        let request = self.edit_reply(chat_id, reply_id, reply, has_share);
         match request.execute().await {
             Ok(_) => debug!("{}> updated", id.0),
             Err(err) => warn!("{}> error updating: {:?}", id.0, err),
         }
     }
 
+    async fn handle_config_command(&self, id: UpdateId, message: &Message, command: Command<'_>) {
+        if let Some(name) = command.bot_name {
+            if name != self.bot.username {
+                return;
+            }
+        }
+        let reply = self.configure_chat(id, message, command).await;
+        let request = self.bot.send_message(message.chat.id, reply);
+        match request.execute().await {
+            Ok(_) => debug!("{}> config reply sent", id.0),
+            Err(err) => warn!("{}> error sending: {:?}", id.0, err),
+        }
+    }
+
+    async fn configure_chat(
+        &self,
+        id: UpdateId,
+        message: &Message,
+        command: Command<'_>,
+    ) -> String {
+        const USAGE: &str = "usage: <code>/evalconfig [--channel=…] [--edition=…] [--mode=…] \
+                             [--output=…] [--lines=…]</code> or <code>/evalconfig reset</code>";
+        let flags = match command.flags {
+            Ok(flags) => flags,
+            Err(err) => return parse::get_flag_error_message(&err),
+        };
+        let mut config = config::get(message.chat.id);
+        let update = match (command.content.trim(), ChatConfig::from_flags(&flags)) {
+            ("", Some(update)) if update == ChatConfig::default() => {
+                return format!("Current settings: {}", config.describe());
+            }
+            ("", Some(update)) => Some(update),
+            ("reset", Some(update)) if update == ChatConfig::default() => None,
+            _ => return USAGE.to_string(),
+        };
+        if !self.can_configure(id, message).await {
+            return "error: only administrators of the chat can change the settings".to_string();
+        }
+        match update {
+            Some(update) => config.merge(update),
+            None => config = ChatConfig::default(),
+        }
+        let description = config.describe();
+        config::set(message.chat.id, config);
+        format!("Settings updated: {description}")
+    }
+
+    /// Check whether the sender of the message can change the settings of the chat.
+    async fn can_configure(&self, id: UpdateId, message: &Message) -> bool {
+        if utils::is_message_from_private_chat(message) {
+            return true;
+        }
+        let from = match &message.from {
+            Some(from) => from,
+            None => return false,
+        };
+        let request = self.bot.get_chat_member(message.chat.id, from.id);
+        match request.execute().await {
+            Ok(member) => matches!(
+                member.status,
+                ChatMemberStatus::Creator | ChatMemberStatus::Administrator
+            ),
+            Err(err) => {
+                warn!("{}> error getting chat member: {:?}", id.0, err);
+                false
+            }
+        }
+    }
+
+    async fn handle_callback_query(&self, id: UpdateId, query: &CallbackQuery) {
+        if query.data.as_deref() != Some(share::CALLBACK_DATA) {
+            return;
+        }
+        let message = match &query.message {
+            Some(message) => message,
+            None => return,
+        };
+        const FAILED: &str = "Failed to share the code, please try again later.";
+        let reply_id = message.message_id;
+        let records = self.records.lock().await;
+        let share = records.find_share(message.chat.id, reply_id).cloned();
+        drop(records);
+        let result = match share {
+            Some(share) => share.create_url(&self.client).await.map_err(|err| {
+                warn!("{}> error creating gist: {:?}", id.0, err);
+                FAILED
+            }),
+            None => Err("The code is too old to be shared."),
+        };
+        let text = match result {
+            Ok(url) => {
+                debug!("{}> sharing as {}", id.0, url);
+                let markup = share::url_markup(url);
+                let request = self
+                    .bot
+                    .edit_reply_markup(message.chat.id, reply_id, markup);
+                match request.execute().await {
+                    Ok(_) => None,
+                    Err(err) => {
+                        warn!("{}> error updating button: {:?}", id.0, err);
+                        Some(FAILED)
+                    }
+                }
+            }
+            Err(text) => Some(text),
+        };
+        let request = self.bot.answer_callback_query(&query.id, text);
+        if let Err(err) = request.execute().await {
+            warn!("{}> error answering callback: {:?}", id.0, err);
+        }
+    }
+
+    /// Update the reply to the result, with the button to share the code if any.
+    fn edit_reply(
+        &self,
+        chat_id: ChatId,
+        reply_id: MessageId,
+        reply: &str,
+        has_share: bool,
+    ) -> BotRequest<Message> {
+        if has_share {
+            let markup = share::callback_markup();
+            self.bot
+                .edit_message_with_markup(chat_id, reply_id, reply, markup)
+        } else {
+            self.bot.edit_message(chat_id, reply_id, reply)
+        }
+    }
+
     fn prepare_command<'p>(
         &'p self,
         id: UpdateId,
         message: &'p Message,
-    ) -> Option<impl Future<Output = Result<String, reqwest::Error>> + 'p> {
+    ) -> Option<Evaluation<impl Future<Output = Result<String, reqwest::Error>> + 'p>> {
         // Don't care about messages not sent from a user.
         let from = message.from.as_ref()?;
         // Don't care about non-text messages.
@@ -149,36 +357,115 @@ impl EvalBot {
             message.message_id.0,
             command
         );
+        let entities = &message.entities;
+        let source = message.reply_to_message.as_deref();
+        self.prepare_eval(id, message, command, entities, source)
+    }
+
+    /// Prepare the evaluation of the command with the given entities
+    /// sent in the chat of the message,
+    /// taking the code from the source message if the command has none.
+    fn prepare_eval<'p>(
+        &'p self,
+        id: UpdateId,
+        message: &'p Message,
+        command: &'p str,
+        entities: &[MessageEntity],
+        source: Option<&'p Message>,
+    ) -> Option<Evaluation<impl Future<Output = Result<String, reqwest::Error>> + 'p>> {
         let is_private = utils::is_message_from_private_chat(message);
         let Command {
             bot_name,
             flags,
             content,
-        } = parse::parse_command(command)?;
+        } = parse::parse_command(command).or_else(|| parse::parse_size_command(command))?;
         if let Some(name) = bot_name {
             if name != self.bot.username {
                 return None;
             }
         }
-        execute::execute(&self.client, content, flags, is_private)
+        // Nothing is evaluated in maintenance mode.
+        if let Some(notice) = maintenance::notice() {
+            return Some(Evaluation {
+                future: future::ok(maintenance::message(&notice)).left_future(),
+                share: None,
+                source: None,
+            });
+        }
+        let mut flags = match flags {
+            Ok(flags) => flags,
+            Err(err) => {
+                let reply = parse::get_flag_error_message(&err);
+                return Some(Evaluation {
+                    future: future::ok(reply).left_future(),
+                    share: None,
+                    source: None,
+                });
+            }
+        };
+        config::get(message.chat.id).apply(&mut flags);
+        let is_query = flags.help || flags.version;
+        let source = source.filter(|_| !is_query && content.trim().is_empty());
+        let (code, source) = match source.and_then(|s| Some((s.text.as_deref()?, s))) {
+            Some((text, source)) => {
+                debug!("{}> code from [{}]", id.0, source.message_id.0);
+                let code = extract::extract_code(text, &source.entities, text);
+                (code, Some(source.message_id))
+            }
+            None => (extract::extract_code(command, entities, content), None),
+        };
+        // Code marked by the client doesn't have its characters mangled.
+        flags.raw |= code.verbatim;
+        let (content, stdin) = (code.text, code.stdin);
+        let share = if is_query {
+            None
+        } else {
+            Share::new(content, stdin, &flags)
+        };
+        let future = execute::execute(&self.client, content, stdin, flags, is_private)?;
+        Some(Evaluation {
+            future: future.right_future(),
+            share,
+            source,
+        })
     }
 }
 
+/// Evaluation prepared for a command.
+struct Evaluation<F> {
+    future: F,
+    /// Code which can be shared from the reply.
+    share: Option<Share>,
+    /// Message the code comes from, if not the command itself.
+    source: Option<MessageId>,
+}
+
+/// Link the command message to the message its code comes from, if any.
+fn make_source(message: &Message, source: Option<MessageId>) -> Option<Source> {
+    Some(Source {
+        msg: source?,
+        command: message.text.clone()?,
+    })
+}
+
 fn generate_reply(reply: Result<String, reqwest::Error>) -> String {
     match reply {
         Ok(reply) => reply,
-        Err(err) => {
-            if err.is_builder() {
-                "error: builder error".into()
-            } else if err.is_redirect() {
-                "error: failed to request".into()
-            } else if err.is_timeout() {
-                "error: timeout".into()
-            } else if let Some(status) = err.status() {
-                format!("error: status code: {status}")
-            } else {
-                "error: unknown error".into()
-            }
-        }
+        Err(err) => describe_error(&err),
+    }
+}
+
+/// Describe the error of requesting the playground, without leaking its details.
+fn describe_error(err: &reqwest::Error) -> String {
+    if err.is_builder() {
+        "error: builder error".into()
+    } else if err.is_redirect() {
+        "error: failed to request".into()
+    } else if err.is_timeout() {
+        "error: timeout".into()
+    } else if let Some(status) = err.status() {
+        format!("error: status code: {status}")
+    } else {
+        "error: unknown error".into()
     }
 }
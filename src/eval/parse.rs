@@ -1,39 +1,92 @@
+use crate::utils;
 use combine::error::StringStreamError;
-use combine::parser::char::{alpha_num, space, spaces, string};
+use combine::parser::char::{alpha_num, letter, space, spaces, string};
 use combine::parser::choice::{choice, optional};
-use combine::parser::combinator::attempt;
+use combine::parser::combinator::{attempt, look_ahead, no_partial};
 use combine::parser::range::recognize;
-use combine::parser::repeat::{many, skip_many1};
-use combine::parser::token::{eof, token};
+use combine::parser::repeat::{many, skip_many, skip_many1};
+use combine::parser::token::{eof, satisfy, token};
 use combine::parser::Parser;
-use serde::Serialize;
-use std::fmt::Write as _;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Write as _};
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct Command<'a> {
     pub bot_name: Option<&'a str>,
-    pub flags: Flags,
+    pub flags: Result<Flags, FlagError>,
     pub content: &'a str,
 }
 
 pub fn parse_command(command: &str) -> Option<Command<'_>> {
+    parse_command_with_name("/eval", command)
+}
+
+/// Parse the `/size` command, which is `/eval --size`.
+pub fn parse_size_command(command: &str) -> Option<Command<'_>> {
+    let mut command = parse_command_with_name("/size", command)?;
+    if let Ok(flags) = &mut command.flags {
+        flags.task = Some(Task::Size);
+    }
+    Some(command)
+}
+
+/// Parse the `/evalconfig` command, which takes the same flags as `/eval`.
+pub fn parse_config_command(command: &str) -> Option<Command<'_>> {
+    parse_command_with_name("/evalconfig", command)
+}
+
+fn parse_command_with_name<'a>(name: &'static str, command: &'a str) -> Option<Command<'a>> {
     let bot_name = token('@').with(recognize(skip_many1(choice((alpha_num(), token('_'))))));
     let spaces1 = || (space(), spaces()).map(|_| ());
-    let flag_name = recognize(skip_many1(alpha_num()));
-    let flag = (spaces1(), choice((string("--"), string("—"))), flag_name).map(|(_, _, name)| name);
-    let mut parser = string("/eval")
+    let value = || recognize(skip_many(satisfy(|c: char| !c.is_whitespace())));
+    let word = || recognize(skip_many1(satisfy(|c: char| !c.is_whitespace())));
+    let flag_name = || recognize((alpha_num(), skip_many(choice((alpha_num(), token('-'))))));
+    let long_prefix = || choice((string("--"), string("—")));
+    let long_flag = (
+        long_prefix(),
+        flag_name(),
+        optional(token('=').with(value())),
+    )
+        .map(|(prefix, name, value)| RawFlag {
+            prefix,
+            name,
+            value,
+        });
+    // Options may also have their values separated by spaces.
+    let option_name = flag_name().and_then(|name| {
+        if OPTION_INFO.iter().any(|info| info.name == name) {
+            Ok(name)
+        } else {
+            Err(StringStreamError::UnexpectedParse)
+        }
+    });
+    let option =
+        (long_prefix(), option_name, spaces1(), word()).map(|(prefix, name, _, value)| RawFlag {
+            prefix,
+            name,
+            value: Some(value),
+        });
+    let short_flag = (
+        token('-'),
+        recognize(letter()),
+        look_ahead(choice((spaces1(), eof()))),
+    )
+        .map(|(_, name, _)| RawFlag {
+            prefix: "-",
+            name,
+            value: None,
+        });
+    // Boxed to keep the type of the whole parser from getting too large to compile.
+    let flag =
+        no_partial(spaces1().with(choice((attempt(option), attempt(long_flag), short_flag))))
+            .boxed();
+    let mut parser = string(name)
         .with((
             optional(bot_name),
             many::<FlagsBuilder, _, _>(attempt(flag)),
         ))
         .skip(choice((spaces1(), eof())))
-        .and_then(|(bot_name, builder)| {
-            if builder.error {
-                Err(StringStreamError::UnexpectedParse)
-            } else {
-                Ok((bot_name, builder.flags))
-            }
-        });
+        .map(|(bot_name, builder)| (bot_name, builder.finish()));
     parser
         .parse(command)
         .ok()
@@ -47,6 +100,9 @@ pub fn parse_command(command: &str) -> Option<Command<'_>> {
 pub fn get_help_message() -> String {
     let mut result = String::new();
     for info in FLAG_INFO.iter() {
+        if let Some(short) = info.short {
+            write!(result, "<code>-{short}</code>, ").unwrap();
+        }
         writeln!(
             result,
             "<code>--{}</code> - {}",
@@ -54,21 +110,108 @@ pub fn get_help_message() -> String {
         )
         .unwrap();
     }
+    for info in OPTION_INFO.iter() {
+        writeln!(
+            result,
+            "<code>--{}={}</code> - {}",
+            info.name, info.values, info.description
+        )
+        .unwrap();
+    }
+    result.push_str(
+        "\nCode using <code>.await</code> outside of async blocks, \
+         or ending with an async block, runs in a tokio runtime.\n",
+    );
+    result
+}
+
+pub fn get_flag_error_message(error: &FlagError) -> String {
+    let mut result = "error: ".to_string();
+    utils::encode_with_code(&mut result, &error.to_string());
+    result.push_str(", see <code>/eval --help</code>");
     result
 }
 
+/// Error in the flags of the command.
+#[derive(Debug, Eq, PartialEq)]
+pub enum FlagError {
+    Unknown(String),
+    MissingValue(String),
+    InvalidValue { flag: String, value: String },
+    UnexpectedValue(String),
+}
+
+impl fmt::Display for FlagError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FlagError::Unknown(flag) => write!(f, "unknown flag `{flag}`"),
+            FlagError::MissingValue(flag) => write!(f, "missing value for `{flag}`"),
+            FlagError::InvalidValue { flag, value } => {
+                write!(f, "invalid value `{value}` for `{flag}`")
+            }
+            FlagError::UnexpectedValue(flag) => write!(f, "`{flag}` doesn't take a value"),
+        }
+    }
+}
+
+/// A flag as written in the command.
+struct RawFlag<'a> {
+    prefix: &'a str,
+    name: &'a str,
+    value: Option<&'a str>,
+}
+
+impl RawFlag<'_> {
+    fn apply(&self, flags: &mut Flags) -> Result<(), FlagError> {
+        let flag = format!("{}{}", self.prefix, self.name);
+        let info = if self.prefix == "-" {
+            let short = self.name.chars().next();
+            FLAG_INFO.iter().find(|info| info.short == short)
+        } else {
+            FLAG_INFO.iter().find(|info| info.name == self.name)
+        };
+        if let Some(info) = info {
+            if self.value.is_some() {
+                return Err(FlagError::UnexpectedValue(flag));
+            }
+            (info.setter)(flags);
+            return Ok(());
+        }
+        let info = OPTION_INFO
+            .iter()
+            .find(|info| info.name == self.name)
+            .ok_or_else(|| FlagError::Unknown(flag.clone()))?;
+        let value = self
+            .value
+            .ok_or_else(|| FlagError::MissingValue(flag.clone()))?;
+        (info.setter)(flags, value).ok_or_else(|| FlagError::InvalidValue {
+            flag,
+            value: value.to_string(),
+        })
+    }
+}
+
 #[derive(Default)]
 struct FlagsBuilder {
     flags: Flags,
-    error: bool,
+    error: Option<FlagError>,
 }
 
-impl<'a> Extend<&'a str> for FlagsBuilder {
-    fn extend<T: IntoIterator<Item = &'a str>>(&mut self, iter: T) {
-        for name in iter {
-            match FLAG_INFO.iter().find(|info| info.name == name) {
-                Some(info) => (info.setter)(&mut self.flags),
-                None => self.error = true,
+impl FlagsBuilder {
+    fn finish(self) -> Result<Flags, FlagError> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.flags),
+        }
+    }
+}
+
+impl<'a> Extend<RawFlag<'a>> for FlagsBuilder {
+    fn extend<T: IntoIterator<Item = RawFlag<'a>>>(&mut self, iter: T) {
+        for flag in iter {
+            if let Err(error) = flag.apply(&mut self.flags) {
+                // Only report the first error.
+                self.error.get_or_insert(error);
             }
         }
     }
@@ -76,92 +219,355 @@ impl<'a> Extend<&'a str> for FlagsBuilder {
 
 struct FlagInfo {
     name: &'static str,
+    short: Option<char>,
     description: &'static str,
     setter: fn(&mut Flags),
 }
 
+/// Information of flags which take a value.
+struct OptionInfo {
+    name: &'static str,
+    values: &'static str,
+    description: &'static str,
+    setter: fn(&mut Flags, &str) -> Option<()>,
+}
+
+const OPTION_INFO: &[OptionInfo] = &[
+    OptionInfo {
+        name: "channel",
+        values: "stable|beta|nightly",
+        description: "use the given channel",
+        setter: |flags, value| {
+            flags.channel = Some(Channel::from_name(value)?);
+            Some(())
+        },
+    },
+    OptionInfo {
+        name: "edition",
+        values: "2015|2018|2021|2024",
+        description: "use the given edition",
+        setter: |flags, value| {
+            flags.edition = Some(Edition::from_name(value)?);
+            Some(())
+        },
+    },
+    OptionInfo {
+        name: "mode",
+        values: "debug|release",
+        description: "do the given build",
+        setter: |flags, value| {
+            flags.mode = Some(Mode::from_name(value)?);
+            Some(())
+        },
+    },
+    OptionInfo {
+        name: "output",
+        values: "short|full",
+        description: "show short or full output",
+        setter: |flags, value| {
+            flags.output = Some(Output::from_name(value)?);
+            Some(())
+        },
+    },
+    OptionInfo {
+        name: "lines",
+        values: "1..50",
+        description: "show at most the given number of lines in short output",
+        setter: |flags, value| {
+            const MAX_LINES: usize = 50;
+            let lines = value.parse().ok().filter(|n| (1..=MAX_LINES).contains(n))?;
+            flags.max_lines = Some(lines);
+            Some(())
+        },
+    },
+];
+
 const FLAG_INFO: &[FlagInfo] = &[
     FlagInfo {
         name: "stable",
+        short: Some('s'),
         description: "use stable channel",
         setter: |flags| flags.channel = Some(Channel::Stable),
     },
     FlagInfo {
         name: "beta",
+        short: Some('b'),
         description: "use beta channel",
         setter: |flags| flags.channel = Some(Channel::Beta),
     },
     FlagInfo {
         name: "nightly",
+        short: Some('n'),
         description: "use nightly channel",
         setter: |flags| flags.channel = Some(Channel::Nightly),
     },
     FlagInfo {
         name: "2015",
+        short: None,
         description: "use 2015 edition",
-        setter: |flags| flags.edition = Some("2015"),
+        setter: |flags| flags.edition = Some(Edition::E2015),
     },
     FlagInfo {
         name: "2018",
+        short: None,
         description: "use 2018 edition",
-        setter: |flags| flags.edition = Some("2018"),
+        setter: |flags| flags.edition = Some(Edition::E2018),
     },
     FlagInfo {
         name: "2021",
+        short: None,
         description: "use 2021 edition",
-        setter: |flags| flags.edition = Some("2021"),
+        setter: |flags| flags.edition = Some(Edition::E2021),
+    },
+    FlagInfo {
+        name: "2024",
+        short: None,
+        description: "use 2024 edition",
+        setter: |flags| flags.edition = Some(Edition::E2024),
     },
     FlagInfo {
         name: "debug",
+        short: Some('d'),
         description: "do debug build",
         setter: |flags| flags.mode = Some(Mode::Debug),
     },
     FlagInfo {
         name: "release",
+        short: Some('r'),
         description: "do release build",
         setter: |flags| flags.mode = Some(Mode::Release),
     },
+    FlagInfo {
+        name: "test",
+        short: Some('t'),
+        description: "run #[test] functions instead of evaluating",
+        setter: |flags| flags.task = Some(Task::Test),
+    },
+    FlagInfo {
+        name: "check",
+        short: None,
+        description: "compile without running and show all errors and warnings",
+        setter: |flags| flags.task = Some(Task::Check),
+    },
+    FlagInfo {
+        name: "clippy",
+        short: None,
+        description: "run clippy instead of evaluating",
+        setter: |flags| flags.task = Some(Task::Clippy),
+    },
+    FlagInfo {
+        name: "miri",
+        short: None,
+        description: "run with Miri to detect undefined behavior (nightly only)",
+        setter: |flags| flags.task = Some(Task::Miri),
+    },
+    FlagInfo {
+        name: "fmt",
+        short: None,
+        description: "format code with rustfmt instead of evaluating",
+        setter: |flags| flags.task = Some(Task::Format),
+    },
+    FlagInfo {
+        name: "asm",
+        short: None,
+        description: "show assembly instead of evaluating",
+        setter: |flags| flags.task = Some(Task::Emit(Target::Asm)),
+    },
+    FlagInfo {
+        name: "llvm-ir",
+        short: None,
+        description: "show LLVM IR instead of evaluating",
+        setter: |flags| flags.task = Some(Task::Emit(Target::LlvmIr)),
+    },
+    FlagInfo {
+        name: "mir",
+        short: None,
+        description: "show MIR instead of evaluating",
+        setter: |flags| flags.task = Some(Task::Emit(Target::Mir)),
+    },
+    FlagInfo {
+        name: "hir",
+        short: None,
+        description: "show HIR instead of evaluating (nightly only)",
+        setter: |flags| flags.task = Some(Task::Emit(Target::Hir)),
+    },
+    FlagInfo {
+        name: "expand",
+        short: None,
+        description: "show code with macros expanded (nightly only)",
+        setter: |flags| flags.task = Some(Task::Expand),
+    },
+    FlagInfo {
+        name: "size",
+        short: None,
+        description: "show size and alignment of the comma-separated types, same as <code>/size</code>",
+        setter: |flags| flags.task = Some(Task::Size),
+    },
+    FlagInfo {
+        name: "niche",
+        short: None,
+        description: "with <code>--size</code>, also show whether <code>Option</code> of the types has the same size",
+        setter: |flags| flags.niche = true,
+    },
+    FlagInfo {
+        name: "pretty",
+        short: None,
+        description: "pretty-print the result with <code>{:#?}</code>",
+        setter: |flags| flags.pretty = true,
+    },
+    FlagInfo {
+        name: "display",
+        short: None,
+        description: "print the result with <code>{}</code> instead of <code>{:?}</code>",
+        setter: |flags| flags.display = true,
+    },
+    FlagInfo {
+        name: "type",
+        short: None,
+        description: "print the type of the result along with it",
+        setter: |flags| flags.show_type = true,
+    },
+    FlagInfo {
+        name: "all-channels",
+        short: None,
+        description: "run on stable, beta and nightly channels and compare the results",
+        setter: |flags| flags.all_channels = true,
+    },
     FlagInfo {
         name: "bare",
+        short: None,
         description: "don't add any wrapping code",
         setter: |flags| flags.bare = true,
     },
     FlagInfo {
         name: "raw",
+        short: None,
         description: "don't convert any Unicode characters automatically",
         setter: |flags| flags.raw = true,
     },
     FlagInfo {
         name: "version",
+        short: Some('v'),
         description: "show version instead of running code",
         setter: |flags| flags.version = true,
     },
     FlagInfo {
         name: "help",
+        short: Some('h'),
         description: "show this help information",
         setter: |flags| flags.help = true,
     },
 ];
 
-#[derive(Debug, Default, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct Flags {
     pub channel: Option<Channel>,
-    pub edition: Option<&'static str>,
+    pub edition: Option<Edition>,
     pub mode: Option<Mode>,
+    pub output: Option<Output>,
+    pub max_lines: Option<usize>,
+    pub task: Option<Task>,
     pub bare: bool,
     pub raw: bool,
+    pub pretty: bool,
+    pub display: bool,
+    pub show_type: bool,
+    pub niche: bool,
+    pub all_channels: bool,
     pub version: bool,
     pub help: bool,
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Mode {
     Debug,
     Release,
 }
 
+impl Mode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "debug" => Some(Mode::Debug),
+            "release" => Some(Mode::Release),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Mode::Debug => "debug",
+            Mode::Release => "release",
+        }
+    }
+}
+
+/// How much of the result to show.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Output {
+    /// Truncated output and summaries, the default in groups.
+    Short,
+    /// Full output and reports, the default in private chats.
+    Full,
+}
+
+impl Output {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "short" => Some(Output::Short),
+            "full" => Some(Output::Full),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Output::Short => "short",
+            Output::Full => "full",
+        }
+    }
+}
+
+/// What to do with the code other than evaluating it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Task {
+    /// Compile as a library and run tests.
+    Test,
+    /// Compile without running and report all diagnostics.
+    Check,
+    /// Run clippy.
+    Clippy,
+    /// Run with Miri.
+    Miri,
+    /// Format with rustfmt.
+    Format,
+    /// Compile and show the output of the given target.
+    Emit(Target),
+    /// Show the code with macros expanded.
+    Expand,
+    /// Show size and alignment of the types.
+    Size,
+}
+
+impl Task {
+    /// Check whether the task is only available on the nightly channel.
+    pub fn is_nightly_only(self) -> bool {
+        matches!(self, Task::Miri | Task::Expand | Task::Emit(Target::Hir))
+    }
+}
+
+/// Compilation output available from the playground.
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Target {
+    Asm,
+    LlvmIr,
+    Mir,
+    Hir,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Channel {
     Stable,
@@ -170,6 +576,15 @@ pub enum Channel {
 }
 
 impl Channel {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "stable" => Some(Channel::Stable),
+            "beta" => Some(Channel::Beta),
+            "nightly" => Some(Channel::Nightly),
+            _ => None,
+        }
+    }
+
     pub fn as_str(self) -> &'static str {
         match self {
             Channel::Stable => "stable",
@@ -179,22 +594,87 @@ impl Channel {
     }
 }
 
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Edition {
+    #[serde(rename = "2015")]
+    E2015,
+    #[serde(rename = "2018")]
+    E2018,
+    #[default]
+    #[serde(rename = "2021")]
+    E2021,
+    #[serde(rename = "2024")]
+    E2024,
+}
+
+impl Edition {
+    const ALL: &'static [Edition] = &[
+        Edition::E2015,
+        Edition::E2018,
+        Edition::E2021,
+        Edition::E2024,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|e| e.as_str() == name)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Edition::E2015 => "2015",
+            Edition::E2018 => "2018",
+            Edition::E2021 => "2021",
+            Edition::E2024 => "2024",
+        }
+    }
+
+    /// The first Rust version, as `(major, minor)`, where the edition is stable.
+    pub fn min_version(self) -> (u32, u32) {
+        match self {
+            Edition::E2015 => (1, 0),
+            Edition::E2018 => (1, 31),
+            Edition::E2021 => (1, 56),
+            Edition::E2024 => (1, 85),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{parse_command, Channel, Command, Flags, Mode};
+    use super::{
+        parse_command, parse_config_command, parse_size_command, Channel, Command, Edition,
+        FlagError, Flags, Mode, Output, Target, Task,
+    };
 
     #[test]
     fn unknown_command() {
         assert_eq!(parse_command("/unknown"), None);
     }
 
+    #[test]
+    fn config_command() {
+        assert_eq!(parse_config_command("/eval --nightly"), None);
+        assert_eq!(parse_command("/evalconfig --nightly"), None);
+        assert_eq!(
+            parse_config_command("/evalconfig --nightly"),
+            Some(Command {
+                bot_name: None,
+                flags: Ok(Flags {
+                    channel: Some(Channel::Nightly),
+                    ..Flags::default()
+                }),
+                content: ""
+            }),
+        );
+    }
+
     #[test]
     fn command_with_nothing() {
         assert_eq!(
             parse_command("/eval"),
             Some(Command {
                 bot_name: None,
-                flags: Flags::default(),
+                flags: Ok(Flags::default()),
                 content: ""
             })
         );
@@ -206,7 +686,7 @@ mod tests {
             parse_command("/eval something after"),
             Some(Command {
                 bot_name: None,
-                flags: Flags::default(),
+                flags: Ok(Flags::default()),
                 content: "something after"
             })
         );
@@ -218,7 +698,7 @@ mod tests {
             parse_command("/eval\nsome content"),
             Some(Command {
                 bot_name: None,
-                flags: Flags::default(),
+                flags: Ok(Flags::default()),
                 content: "some content"
             }),
         );
@@ -226,7 +706,167 @@ mod tests {
 
     #[test]
     fn unknown_flag() {
-        assert_eq!(parse_command("/eval --unknown"), None);
+        assert_eq!(
+            parse_command("/eval --unknown"),
+            Some(Command {
+                bot_name: None,
+                flags: Err(FlagError::Unknown("--unknown".to_string())),
+                content: ""
+            }),
+        );
+    }
+
+    #[test]
+    fn flags_with_value() {
+        let expected_flags = Flags {
+            channel: Some(Channel::Nightly),
+            edition: Some(Edition::E2018),
+            mode: Some(Mode::Release),
+            ..Flags::default()
+        };
+        assert_eq!(
+            parse_command("/eval --channel nightly --edition=2018 —mode=release content"),
+            Some(Command {
+                bot_name: None,
+                flags: Ok(expected_flags),
+                content: "content"
+            }),
+        );
+    }
+
+    #[test]
+    fn output_flags() {
+        let expected_flags = Flags {
+            output: Some(Output::Short),
+            max_lines: Some(10),
+            ..Flags::default()
+        };
+        assert_eq!(
+            parse_command("/eval --output=short --lines 10"),
+            Some(Command {
+                bot_name: None,
+                flags: Ok(expected_flags),
+                content: ""
+            }),
+        );
+        assert_eq!(
+            parse_command("/eval --lines=0").unwrap().flags,
+            Err(FlagError::InvalidValue {
+                flag: "--lines".to_string(),
+                value: "0".to_string(),
+            }),
+        );
+    }
+
+    #[test]
+    fn format_flags() {
+        let expected_flags = Flags {
+            pretty: true,
+            display: true,
+            show_type: true,
+            ..Flags::default()
+        };
+        assert_eq!(
+            parse_command("/eval --pretty --display --type [1, 2]"),
+            Some(Command {
+                bot_name: None,
+                flags: Ok(expected_flags),
+                content: "[1, 2]"
+            }),
+        );
+    }
+
+    #[test]
+    fn all_channels_flag() {
+        assert_eq!(
+            parse_command("/eval --all-channels --release 1")
+                .unwrap()
+                .flags,
+            Ok(Flags {
+                mode: Some(Mode::Release),
+                all_channels: true,
+                ..Flags::default()
+            }),
+        );
+    }
+
+    #[test]
+    fn size_command() {
+        assert_eq!(
+            parse_size_command("/size --niche Option<Box<[u8]>>, u8"),
+            Some(Command {
+                bot_name: None,
+                flags: Ok(Flags {
+                    task: Some(Task::Size),
+                    niche: true,
+                    ..Flags::default()
+                }),
+                content: "Option<Box<[u8]>>, u8",
+            }),
+        );
+        assert_eq!(parse_size_command("/eval u8"), None);
+    }
+
+    #[test]
+    fn short_flags() {
+        let expected_flags = Flags {
+            channel: Some(Channel::Nightly),
+            mode: Some(Mode::Release),
+            ..Flags::default()
+        };
+        assert_eq!(
+            parse_command("/eval -n -r code"),
+            Some(Command {
+                bot_name: None,
+                flags: Ok(expected_flags),
+                content: "code"
+            }),
+        );
+        // Not a flag.
+        assert_eq!(
+            parse_command("/eval -n -abs"),
+            Some(Command {
+                bot_name: None,
+                flags: Ok(Flags {
+                    channel: Some(Channel::Nightly),
+                    ..Flags::default()
+                }),
+                content: "-abs"
+            }),
+        );
+    }
+
+    #[test]
+    fn flag_errors() {
+        let tests = [
+            ("/eval -x", FlagError::Unknown("-x".to_string())),
+            (
+                "/eval --channel",
+                FlagError::MissingValue("--channel".to_string()),
+            ),
+            (
+                "/eval --mode=fast code",
+                FlagError::InvalidValue {
+                    flag: "--mode".to_string(),
+                    value: "fast".to_string(),
+                },
+            ),
+            (
+                "/eval --channel= code",
+                FlagError::InvalidValue {
+                    flag: "--channel".to_string(),
+                    value: "".to_string(),
+                },
+            ),
+            (
+                "/eval --nightly=yes --unknown",
+                FlagError::UnexpectedValue("--nightly".to_string()),
+            ),
+        ];
+        for (input, error) in tests {
+            let command = parse_command(input).unwrap();
+            assert_eq!(command.flags, Err(error), "{input}");
+        }
     }
 
     #[test]
@@ -245,7 +885,7 @@ mod tests {
                 parse_command(&format!("/eval --{}", name)),
                 Some(Command {
                     bot_name: None,
-                    flags: expected_flags,
+                    flags: Ok(expected_flags),
                     content: ""
                 }),
             );
@@ -254,17 +894,22 @@ mod tests {
 
     #[test]
     fn edition_flags() {
-        const EDITIONS: &[&str] = &["2015", "2018"];
+        const EDITIONS: &[Edition] = &[
+            Edition::E2015,
+            Edition::E2018,
+            Edition::E2021,
+            Edition::E2024,
+        ];
         for edition in EDITIONS.iter() {
             let expected_flags = Flags {
                 edition: Some(*edition),
                 ..Flags::default()
             };
             assert_eq!(
-                parse_command(&format!("/eval --{}", edition)),
+                parse_command(&format!("/eval --{}", edition.as_str())),
                 Some(Command {
                     bot_name: None,
-                    flags: expected_flags,
+                    flags: Ok(expected_flags),
                     content: ""
                 }),
             );
@@ -283,7 +928,37 @@ mod tests {
                 parse_command(&format!("/eval --{}", name)),
                 Some(Command {
                     bot_name: None,
-                    flags: expected_flags,
+                    flags: Ok(expected_flags),
+                    content: ""
+                }),
+            );
+        }
+    }
+
+    #[test]
+    fn task_flags() {
+        const TASKS: &[(&str, Task)] = &[
+            ("test", Task::Test),
+            ("check", Task::Check),
+            ("clippy", Task::Clippy),
+            ("miri", Task::Miri),
+            ("fmt", Task::Format),
+            ("asm", Task::Emit(Target::Asm)),
+            ("llvm-ir", Task::Emit(Target::LlvmIr)),
+            ("mir", Task::Emit(Target::Mir)),
+            ("hir", Task::Emit(Target::Hir)),
+            ("expand", Task::Expand),
+        ];
+        for (name, task) in TASKS.iter() {
+            let expected_flags = Flags {
+                task: Some(*task),
+                ..Flags::default()
+            };
+            assert_eq!(
+                parse_command(&format!("/eval --{}", name)),
+                Some(Command {
+                    bot_name: None,
+                    flags: Ok(expected_flags),
                     content: ""
                 }),
             );
@@ -300,7 +975,7 @@ mod tests {
             parse_command("/eval --bare"),
             Some(Command {
                 bot_name: None,
-                flags: expected_flags,
+                flags: Ok(expected_flags),
                 content: ""
             }),
         );
@@ -316,7 +991,7 @@ mod tests {
             parse_command("/eval --version"),
             Some(Command {
                 bot_name: None,
-                flags: expected_flags,
+                flags: Ok(expected_flags),
                 content: ""
             })
         );
@@ -332,7 +1007,7 @@ mod tests {
             parse_command("/eval --help"),
             Some(Command {
                 bot_name: None,
-                flags: expected_flags,
+                flags: Ok(expected_flags),
                 content: ""
             })
         );
@@ -340,7 +1015,10 @@ mod tests {
 
     #[test]
     fn flags_without_sep() {
-        assert_eq!(parse_command("/eval --stable--2015"), None);
+        assert_eq!(
+            parse_command("/eval --stable--2015").unwrap().flags,
+            Err(FlagError::Unknown("--stable--2015".to_string())),
+        );
     }
 
     #[test]
@@ -349,9 +1027,17 @@ mod tests {
         let expected_flags = Flags {
             channel: Some(Channel::Nightly),
             mode: Some(Mode::Debug),
-            edition: Some("2015"),
+            edition: Some(Edition::E2015),
+            output: None,
+            max_lines: None,
+            task: None,
             bare: true,
             raw: false,
+            pretty: false,
+            display: false,
+            show_type: false,
+            niche: false,
+            all_channels: false,
             version: true,
             help: false,
         };
@@ -359,7 +1045,7 @@ mod tests {
             parse_command(input),
             Some(Command {
                 bot_name: None,
-                flags: expected_flags,
+                flags: Ok(expected_flags),
                 content: "rest\ncontent"
             })
         );
@@ -371,10 +1057,10 @@ mod tests {
             parse_command("/eval@bot --bare content"),
             Some(Command {
                 bot_name: Some("bot"),
-                flags: Flags {
+                flags: Ok(Flags {
                     bare: true,
                     ..Flags::default()
-                },
+                }),
                 content: "content",
             })
         );
@@ -386,11 +1072,11 @@ mod tests {
             parse_command("/eval@bot --bare —raw content"),
             Some(Command {
                 bot_name: Some("bot"),
-                flags: Flags {
+                flags: Ok(Flags {
                     bare: true,
                     raw: true,
                     ..Flags::default()
-                },
+                }),
                 content: "content",
             })
         );
@@ -37,3 +37,26 @@ use std::sync::{Arc, Mutex, RwLock};
 fn type_name_of_val<T: ?Sized>(_: &T) -> &'static str {
     type_name::<T>()
 }
+
+// The value of the code is printed through `(&(&value,)).__eval_printable()`,
+// which resolves to the impl for `()` before the one for any type needing another reference,
+// so that `()` is skipped without being formatted.
+trait __EvalSkipUnit {
+    fn __eval_printable(&self) -> Option<&'static str>;
+}
+
+impl __EvalSkipUnit for (&(),) {
+    fn __eval_printable(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+trait __EvalPrintable<'a, T: ?Sized> {
+    fn __eval_printable(&self) -> Option<&'a T>;
+}
+
+impl<'a, T: ?Sized> __EvalPrintable<'a, T> for &(&'a T,) {
+    fn __eval_printable(&self) -> Option<&'a T> {
+        Some(self.0)
+    }
+}
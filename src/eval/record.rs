@@ -1,9 +1,10 @@
+use super::share::Share;
 use log::error;
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::fs::File;
 use std::io;
-use telegram_types::bot::types::{MessageId, Time};
+use telegram_types::bot::types::{ChatId, MessageId, Time};
 
 const RECORD_LIST_FILE: &str = "record_list.json";
 
@@ -28,34 +29,86 @@ impl RecordService {
     }
 
     /// Push a new record with reply being empty.
-    pub fn push_record(&mut self, msg: MessageId, date: Time) {
+    pub fn push_record(&mut self, chat: ChatId, msg: MessageId, date: Time) {
+        let chat = Some(chat);
         let reply = None;
-        self.0.push_back(Record { msg, reply, date });
+        let share = None;
+        let source = None;
+        self.0.push_back(Record {
+            chat,
+            msg,
+            reply,
+            date,
+            share,
+            source,
+        });
     }
 
-    fn find_record(&self, msg: MessageId) -> Option<&Record> {
-        self.0.iter().rev().find(|r| r.msg == msg)
+    fn find_record(&self, chat: ChatId, msg: MessageId) -> Option<&Record> {
+        self.0.iter().rev().find(|r| r.is_in(chat) && r.msg == msg)
     }
 
-    fn find_record_mut(&mut self, msg: MessageId) -> Option<&mut Record> {
-        self.0.iter_mut().rev().find(|r| r.msg == msg)
+    fn find_record_mut(&mut self, chat: ChatId, msg: MessageId) -> Option<&mut Record> {
+        self.0
+            .iter_mut()
+            .rev()
+            .find(|r| r.is_in(chat) && r.msg == msg)
     }
 
     /// Find the reply message of the given record.
-    pub fn find_reply(&self, msg: MessageId) -> Option<MessageId> {
-        self.find_record(msg).and_then(|r| r.reply)
+    pub fn find_reply(&self, chat: ChatId, msg: MessageId) -> Option<MessageId> {
+        self.find_record(chat, msg).and_then(|r| r.reply)
     }
 
     /// Set the reply message of the given record.
-    pub fn set_reply(&mut self, msg: MessageId, reply: MessageId) {
-        if let Some(r) = self.find_record_mut(msg) {
+    pub fn set_reply(&mut self, chat: ChatId, msg: MessageId, reply: MessageId) {
+        if let Some(r) = self.find_record_mut(chat, msg) {
             r.reply = Some(reply);
         }
     }
 
+    /// Set the code which can be shared from the reply of the given record.
+    pub fn set_share(&mut self, chat: ChatId, msg: MessageId, share: Option<Share>) {
+        if let Some(r) = self.find_record_mut(chat, msg) {
+            r.share = share;
+        }
+    }
+
+    /// Set the message the code of the given record comes from.
+    pub fn set_source(&mut self, chat: ChatId, msg: MessageId, source: Option<Source>) {
+        if let Some(r) = self.find_record_mut(chat, msg) {
+            r.source = source;
+        }
+    }
+
+    /// Find the record whose code comes from the given message in the chat,
+    /// returning the command message, its reply and the source.
+    pub fn find_by_source(
+        &self,
+        chat: ChatId,
+        source: MessageId,
+    ) -> Option<(MessageId, MessageId, &Source)> {
+        self.0.iter().rev().find_map(|r| {
+            if !r.is_in(chat) {
+                return None;
+            }
+            let s = r.source.as_ref().filter(|s| s.msg == source)?;
+            Some((r.msg, r.reply?, s))
+        })
+    }
+
+    /// Find the code which can be shared from the given reply message in the chat.
+    pub fn find_share(&self, chat: ChatId, reply: MessageId) -> Option<&Share> {
+        self.0
+            .iter()
+            .rev()
+            .find(|r| r.is_in(chat) && r.reply == Some(reply))
+            .and_then(|r| r.share.as_ref())
+    }
+
     /// Remove the reply message of the given record.
-    pub fn remove_reply(&mut self, msg: MessageId) {
-        if let Some(r) = self.find_record_mut(msg) {
+    pub fn remove_reply(&mut self, chat: ChatId, msg: MessageId) {
+        if let Some(r) = self.find_record_mut(chat, msg) {
             r.reply = None;
         }
     }
@@ -88,7 +141,91 @@ impl Drop for RecordService {
 
 #[derive(Deserialize, Serialize)]
 struct Record {
+    /// Chat of the messages, as message ids are only unique in a chat.
+    /// It's `None` for records saved before it was recorded.
+    #[serde(default)]
+    chat: Option<ChatId>,
     msg: MessageId,
     reply: Option<MessageId>,
     date: Time,
+    #[serde(default)]
+    share: Option<Share>,
+    #[serde(default)]
+    source: Option<Source>,
+}
+
+impl Record {
+    /// Check whether the record is of the given chat.
+    ///
+    /// Records without the chat only have the message id to match.
+    fn is_in(&self, chat: ChatId) -> bool {
+        self.chat.is_none_or(|c| c == chat)
+    }
+}
+
+/// Message replied to by a command without code, which the code comes from.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Source {
+    pub msg: MessageId,
+    /// Text of the command, to evaluate again when the source is edited.
+    pub command: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::ManuallyDrop;
+
+    #[test]
+    fn test_find_share() {
+        // The records are not dropped, so that they are not saved to the file.
+        let mut records = ManuallyDrop::new(RecordService(VecDeque::new()));
+        let share = Share::new("1", None, &Default::default()).unwrap();
+        let (chat_a, chat_b) = (ChatId(1), ChatId(2));
+        records.push_record(chat_a, MessageId(10), Time(0));
+        records.set_reply(chat_a, MessageId(10), MessageId(11));
+        records.set_share(chat_a, MessageId(10), Some(share));
+        assert!(records.find_share(chat_a, MessageId(11)).is_some());
+        // The same message id in another chat is a different message.
+        assert!(records.find_share(chat_b, MessageId(11)).is_none());
+    }
+
+    #[test]
+    fn test_find_by_source() {
+        let mut records = ManuallyDrop::new(RecordService(VecDeque::new()));
+        let (chat_a, chat_b) = (ChatId(1), ChatId(2));
+        let source = Source {
+            msg: MessageId(5),
+            command: "/eval".to_string(),
+        };
+        records.push_record(chat_a, MessageId(10), Time(0));
+        records.set_reply(chat_a, MessageId(10), MessageId(11));
+        records.set_source(chat_a, MessageId(10), Some(source));
+        let (msg, reply, source) = records.find_by_source(chat_a, MessageId(5)).unwrap();
+        assert_eq!(
+            (msg, reply, source.msg),
+            (MessageId(10), MessageId(11), MessageId(5))
+        );
+        // Editing a message with the same id in another chat doesn't affect the record.
+        assert!(records.find_by_source(chat_b, MessageId(5)).is_none());
+        assert_eq!(records.find_reply(chat_b, MessageId(10)), None);
+        records.set_reply(chat_b, MessageId(10), MessageId(12));
+        assert_eq!(
+            records.find_reply(chat_a, MessageId(10)),
+            Some(MessageId(11))
+        );
+    }
+
+    #[test]
+    fn test_legacy_record() {
+        // Records saved before the chat was recorded still match by the message.
+        let list = r#"[{"msg": 10, "reply": 11, "date": 0}]"#;
+        let mut records = ManuallyDrop::new(RecordService(serde_json::from_str(list).unwrap()));
+        assert_eq!(
+            records.find_reply(ChatId(1), MessageId(10)),
+            Some(MessageId(11))
+        );
+        records.remove_reply(ChatId(1), MessageId(10));
+        assert_eq!(records.find_reply(ChatId(1), MessageId(10)), None);
+    }
 }
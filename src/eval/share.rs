@@ -0,0 +1,202 @@
+use super::execute;
+use super::parse::{Channel, Edition, Flags, Mode, Task};
+use serde::{Deserialize, Serialize};
+use telegram_types::bot::types::{
+    InlineKeyboardButton, InlineKeyboardButtonPressed, InlineKeyboardMarkup,
+};
+
+/// Callback data of the button to share the code.
+pub const CALLBACK_DATA: &str = "playground";
+
+const BUTTON_TEXT: &str = "Open in Playground";
+
+/// Evaluated code which can be shared to the playground on request.
+///
+/// Only the code of the user is kept, and it's wrapped when shared.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Share {
+    code: String,
+    #[serde(default)]
+    stdin: Option<String>,
+    channel: Channel,
+    mode: Mode,
+    edition: Edition,
+    #[serde(default)]
+    wrap: WrapFlags,
+}
+
+/// Flags affecting how the code is wrapped.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+struct WrapFlags {
+    bare: bool,
+    raw: bool,
+    test: bool,
+    size: bool,
+    niche: bool,
+    pretty: bool,
+    display: bool,
+    show_type: bool,
+}
+
+impl Share {
+    /// Keep the code to share if the evaluation can be reproduced on the playground,
+    /// which is the case for running the code or its tests on a single channel.
+    pub fn new(code: &str, stdin: Option<&str>, flags: &Flags) -> Option<Self> {
+        let reproducible = matches!(flags.task, None | Some(Task::Test | Task::Size));
+        if !reproducible || flags.all_channels {
+            return None;
+        }
+        Some(Share {
+            code: code.to_string(),
+            stdin: stdin.map(str::to_string),
+            channel: flags.channel.unwrap_or(Channel::Stable),
+            mode: flags.mode.unwrap_or(Mode::Debug),
+            edition: flags.edition.unwrap_or_default(),
+            wrap: WrapFlags {
+                bare: flags.bare,
+                raw: flags.raw,
+                test: flags.task == Some(Task::Test),
+                size: flags.task == Some(Task::Size),
+                niche: flags.niche,
+                pretty: flags.pretty,
+                display: flags.display,
+                show_type: flags.show_type,
+            },
+        })
+    }
+
+    /// Generate the code to share, which runs the same as evaluated.
+    async fn generate_code(&self) -> String {
+        let wrap = &self.wrap;
+        let task = match (wrap.test, wrap.size) {
+            (true, _) => Some(Task::Test),
+            (_, true) => Some(Task::Size),
+            _ => None,
+        };
+        let flags = Flags {
+            channel: Some(self.channel),
+            edition: Some(self.edition),
+            mode: Some(self.mode),
+            task,
+            bare: wrap.bare,
+            raw: wrap.raw,
+            niche: wrap.niche,
+            pretty: wrap.pretty,
+            display: wrap.display,
+            show_type: wrap.show_type,
+            ..Flags::default()
+        };
+        execute::code_to_share(&self.code, self.stdin.as_deref(), &flags).await
+    }
+
+    /// Create a gist of the code and get the playground URL to it.
+    pub async fn create_url(&self, client: &reqwest::Client) -> Result<String, reqwest::Error> {
+        #[derive(Serialize)]
+        struct GistRequest<'a> {
+            code: &'a str,
+        }
+        #[derive(Deserialize)]
+        struct GistResponse {
+            id: String,
+        }
+        const URL: &str = "https://play.rust-lang.org/meta/gist";
+        let code = self.generate_code().await;
+        let req = GistRequest { code: &code };
+        let resp = client.post(URL).json(&req).send().await?;
+        let gist: GistResponse = resp.error_for_status()?.json().await?;
+        Ok(self.playground_url(&gist.id))
+    }
+
+    fn playground_url(&self, gist_id: &str) -> String {
+        format!(
+            "https://play.rust-lang.org/?version={}&mode={}&edition={}&gist={}",
+            self.channel.as_str(),
+            self.mode.as_str(),
+            self.edition.as_str(),
+            gist_id,
+        )
+    }
+}
+
+/// Keyboard with the button to request sharing.
+pub fn callback_markup() -> InlineKeyboardMarkup {
+    markup(InlineKeyboardButtonPressed::CallbackData(
+        CALLBACK_DATA.to_string(),
+    ))
+}
+
+/// Keyboard with the button linking to the playground.
+pub fn url_markup(url: String) -> InlineKeyboardMarkup {
+    markup(InlineKeyboardButtonPressed::Url(url))
+}
+
+fn markup(pressed: InlineKeyboardButtonPressed) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup {
+        inline_keyboard: vec![vec![InlineKeyboardButton {
+            text: BUTTON_TEXT.to_string(),
+            pressed,
+        }]],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::parse::Target;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_playground_url() {
+        let flags = Flags {
+            channel: Some(Channel::Nightly),
+            mode: Some(Mode::Release),
+            bare: true,
+            ..Flags::default()
+        };
+        let share = Share::new("fn main() {}", None, &flags).unwrap();
+        assert_eq!(block_on(share.generate_code()), "fn main() {}");
+        assert_eq!(
+            share.playground_url("0123abcd"),
+            "https://play.rust-lang.org/?version=nightly&mode=release&edition=2021&gist=0123abcd",
+        );
+    }
+
+    #[test]
+    fn test_generate_code() {
+        let flags = Flags {
+            pretty: true,
+            ..Flags::default()
+        };
+        let share = Share::new("vec![1]", Some("input"), &flags).unwrap();
+        // Only the code of the user is stored.
+        assert_eq!(share.code, "vec![1]");
+        let code = block_on(share.generate_code());
+        assert!(code.contains("println!(\"{:#?}\""));
+        assert!(code.contains("__eval_feed_stdin(\"input\");"));
+    }
+
+    #[test]
+    fn test_unreproducible() {
+        let tasks = [
+            Task::Check,
+            Task::Clippy,
+            Task::Miri,
+            Task::Format,
+            Task::Emit(Target::Asm),
+            Task::Expand,
+        ];
+        for task in tasks {
+            let flags = Flags {
+                task: Some(task),
+                ..Flags::default()
+            };
+            assert!(Share::new("1", None, &flags).is_none(), "task: {task:?}");
+        }
+        let flags = Flags {
+            all_channels: true,
+            ..Flags::default()
+        };
+        assert!(Share::new("1", None, &flags).is_none());
+    }
+}
@@ -0,0 +1,168 @@
+use super::diagnostic::Location;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Map from locations in the generated code to locations in the user's code.
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    segments: Vec<Segment>,
+}
+
+/// A piece of the user's code put into the generated code.
+#[derive(Debug)]
+struct Segment {
+    /// Where the piece starts in the generated code.
+    generated: Location,
+    /// Where the piece ends in the generated code.
+    generated_end: Location,
+    /// Where the piece starts in the user's code.
+    user: Location,
+}
+
+impl SourceMap {
+    /// Map the location in the generated code to the user's code.
+    ///
+    /// Returns `None` if the location points into the template.
+    pub fn map(&self, location: Location) -> Option<Location> {
+        let position = |l: Location| (l.line, l.column);
+        let segment = self.segments.iter().find(|s| {
+            position(location) >= position(s.generated)
+                && position(location) <= position(s.generated_end)
+        })?;
+        let line_offset = location.line - segment.generated.line;
+        let column = if line_offset == 0 {
+            let column = location.column.checked_sub(segment.generated.column)?;
+            column + segment.user.column
+        } else {
+            location.column
+        };
+        Some(Location {
+            line: segment.user.line + line_offset,
+            column,
+        })
+    }
+
+    /// Rewrite locations in the generated code mentioned in the output,
+    /// e.g. `--> src/main.rs:L:C` from rustc and `panicked at src/main.rs:L:C`,
+    /// to locations in the user's code.
+    ///
+    /// Locations pointing into the template are removed.
+    pub fn rewrite_locations<'a>(&self, output: &'a str) -> Cow<'a, str> {
+        static RE_LOCATION: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r"(, | at | *--> )?src/(?:main|lib)\.rs:(\d+):(\d+)").unwrap());
+        RE_LOCATION.replace_all(output, |captures: &Captures<'_>| {
+            let number = |i| captures.get(i).unwrap().as_str().parse().unwrap_or(0);
+            let location = Location {
+                line: number(2),
+                column: number(3),
+            };
+            match self.map(location) {
+                Some(location) => {
+                    let prefix = captures.get(1).map_or("", |m| m.as_str());
+                    format!("{}{}:{}", prefix, location.line, location.column)
+                }
+                None => String::new(),
+            }
+        })
+    }
+}
+
+/// Builder of generated code which records where the user's code is put.
+pub struct CodeBuilder<'a> {
+    user_code: &'a str,
+    code: String,
+    map: SourceMap,
+}
+
+impl<'a> CodeBuilder<'a> {
+    pub fn new(user_code: &'a str) -> Self {
+        CodeBuilder {
+            user_code,
+            code: String::new(),
+            map: SourceMap::default(),
+        }
+    }
+
+    /// Append code which is not from the user.
+    pub fn push_template(&mut self, text: &str) {
+        self.code.push_str(text);
+    }
+
+    /// Append the given range of the user's code.
+    pub fn push_user(&mut self, range: Range<usize>) {
+        let text = &self.user_code[range.clone()];
+        let generated = end_location(&self.code);
+        self.code.push_str(text);
+        if !text.is_empty() {
+            self.map.segments.push(Segment {
+                generated,
+                generated_end: end_location(&self.code),
+                user: end_location(&self.user_code[..range.start]),
+            });
+        }
+    }
+
+    pub fn finish(self) -> (String, SourceMap) {
+        (self.code, self.map)
+    }
+}
+
+/// Get the location right after the end of the given text.
+fn end_location(text: &str) -> Location {
+    let line_start = text.rfind('\n').map_or(0, |pos| pos + 1);
+    Location {
+        line: text.matches('\n').count() + 1,
+        column: text[line_start..].chars().count() + 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loc(line: usize, column: usize) -> Location {
+        Location { line, column }
+    }
+
+    #[test]
+    fn test_source_map() {
+        let user_code = "#![feature(test)] let a = 1;\na + 1";
+        let header_len = "#![feature(test)] ".len();
+        let mut builder = CodeBuilder::new(user_code);
+        builder.push_template("// template\n");
+        builder.push_user(0..header_len);
+        builder.push_template("\nfn main() {\n    ");
+        builder.push_user(header_len..user_code.len());
+        builder.push_template("\n}\n");
+        let (code, map) = builder.finish();
+        assert_eq!(
+            code,
+            "// template\n#![feature(test)] \nfn main() {\n    let a = 1;\na + 1\n}\n"
+        );
+        // Template.
+        assert_eq!(map.map(loc(1, 1)), None);
+        assert_eq!(map.map(loc(3, 1)), None);
+        assert_eq!(map.map(loc(4, 2)), None);
+        assert_eq!(map.map(loc(6, 1)), None);
+        // Header.
+        assert_eq!(map.map(loc(2, 4)), Some(loc(1, 4)));
+        // Body.
+        assert_eq!(map.map(loc(4, 5)), Some(loc(1, 19)));
+        assert_eq!(map.map(loc(4, 9)), Some(loc(1, 23)));
+        assert_eq!(map.map(loc(5, 3)), Some(loc(2, 3)));
+
+        assert_eq!(
+            map.rewrite_locations(
+                "thread 'main' panicked at src/main.rs:5:1:\n\
+                 explicit panic\n \
+                 --> src/main.rs:6:1\n\
+                 thread 'main' panicked at 'old', src/main.rs:4:5",
+            ),
+            "thread 'main' panicked at 2:1:\n\
+             explicit panic\n\n\
+             thread 'main' panicked at 'old', 1:19",
+        );
+    }
+}
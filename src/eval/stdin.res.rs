@@ -0,0 +1,19 @@
+/// Run the program again with the input fed into its stdin.
+fn __eval_feed_stdin(input: &str) {
+    use std::io::Write as _;
+    use std::process::{Command, Stdio};
+    const ENV: &str = "__EVAL_STDIN";
+    if std::env::var_os(ENV).is_some() {
+        return;
+    }
+    let exe = std::env::current_exe().expect("failed to feed stdin");
+    let mut child = Command::new(exe)
+        .env(ENV, "1")
+        .stdin(Stdio::piped())
+        .spawn()
+        .expect("failed to feed stdin");
+    // The program may exit without reading all the input, which is fine.
+    let _ = child.stdin.take().unwrap().write_all(input.as_bytes());
+    let status = child.wait().expect("failed to feed stdin");
+    std::process::exit(status.code().unwrap_or(101));
+}
@@ -0,0 +1,706 @@
+use futures::channel::oneshot;
+use once_cell::sync::Lazy;
+use once_cell::unsync::OnceCell;
+use proc_macro2::{TokenStream, TokenTree};
+use quote::ToTokens;
+use regex::Regex;
+use std::collections::HashSet;
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::thread;
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::visit::{self, Visit};
+use syn::{
+    BinOp, Block, Expr, ExprAsync, ExprAwait, ExprClosure, File, Item, Macro, Stmt, Token, Type,
+    UseTree, Visibility,
+};
+
+/// Maximum number of characters of code to parse, which is the limit of a message.
+const MAX_CODE_LEN: usize = 4096;
+
+/// Stack size of the thread parsing code.
+///
+/// Parsing recurses for each level of nesting in the code,
+/// so the stack needs to be large enough for the deepest nesting
+/// possible in code of `MAX_CODE_LEN`.
+const PARSER_STACK_SIZE: usize = 256 * 1024 * 1024;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Sender of jobs to the thread parsing code, which lives as long as the program.
+static PARSER: Lazy<mpsc::Sender<Job>> = Lazy::new(|| {
+    let (sender, receiver) = mpsc::channel::<Job>();
+    thread::Builder::new()
+        .name("parser".to_string())
+        .stack_size(PARSER_STACK_SIZE)
+        .spawn(move || {
+            for job in receiver {
+                // A panicking job drops its result sender, which the caller notices.
+                let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                // Source of spans is kept in the thread until invalidated.
+                proc_macro2::extra::invalidate_current_thread_spans();
+            }
+        })
+        .expect("failed to spawn parser thread");
+    sender
+});
+
+/// Parse the code on the parser thread, and get the result of the function analyzing it.
+///
+/// `body_start` is where statements of the code start, after its headers.
+/// Syntax trees can't leave the parser thread, so everything needing them
+/// should be done by the function, which gets the code parsed only once.
+pub async fn parse<T: Send + 'static>(
+    code: String,
+    body_start: usize,
+    f: impl FnOnce(&Parsed<'_>) -> T + Send + 'static,
+) -> T {
+    let (sender, receiver) = oneshot::channel();
+    let job = Box::new(move || {
+        let parsed = Parsed::new(&code, body_start);
+        let _ = sender.send(f(&parsed));
+    });
+    PARSER.send(job).expect("parser thread exited");
+    receiver.await.expect("parsing code panicked")
+}
+
+/// Code being parsed on the parser thread.
+///
+/// Each syntax tree is parsed on first use, and shared by the analyses.
+/// Code longer than `MAX_CODE_LEN` is not parsed at all.
+pub struct Parsed<'a> {
+    code: &'a str,
+    body_start: usize,
+    file: OnceCell<Option<File>>,
+    body: OnceCell<Option<Vec<Stmt>>>,
+}
+
+/// Structure of code consisting of statements.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Body {
+    /// Ranges of items which can be put at the module level.
+    pub items: Vec<Range<usize>>,
+    /// Range of the trailing expression whose value should be printed.
+    pub expr: Option<Range<usize>>,
+    /// Whether the code awaits at the top level, so it needs to run in an async runtime.
+    pub is_async: bool,
+    /// Whether the trailing expression is an async block, whose output should be printed.
+    pub expr_is_async: bool,
+}
+
+impl<'a> Parsed<'a> {
+    fn new(code: &'a str, body_start: usize) -> Self {
+        Parsed {
+            code,
+            body_start,
+            file: OnceCell::new(),
+            body: OnceCell::new(),
+        }
+    }
+
+    /// Parse other code on the parser thread as well.
+    pub fn parse_other<'b>(&self, code: &'b str, body_start: usize) -> Parsed<'b> {
+        Parsed::new(code, body_start)
+    }
+
+    pub fn code(&self) -> &'a str {
+        self.code
+    }
+
+    fn is_too_long(&self) -> bool {
+        self.code.chars().count() > MAX_CODE_LEN
+    }
+
+    /// Get the code parsed as a file of items.
+    fn file(&self) -> Option<&File> {
+        self.file
+            .get_or_init(|| {
+                if self.is_too_long() {
+                    return None;
+                }
+                syn::parse_file(self.code).ok()
+            })
+            .as_ref()
+    }
+
+    /// Get the body of the code parsed as statements,
+    /// whose ranges are relative to the body.
+    fn body(&self) -> Option<&[Stmt]> {
+        self.body
+            .get_or_init(|| {
+                if self.is_too_long() {
+                    return None;
+                }
+                let body = &self.code[self.body_start..];
+                Block::parse_within.parse_str(body).ok()
+            })
+            .as_deref()
+    }
+
+    /// Analyze the body of the code as statements of a function body.
+    ///
+    /// Returns `None` if the body fails to parse.
+    pub fn analyze_body(&self, reserved: &HashSet<String>) -> Option<Body> {
+        let stmts = self.body()?;
+        let offset =
+            |range: Range<usize>| range.start + self.body_start..range.end + self.body_start;
+        let mut body = Body::default();
+        let mut hoistable = true;
+        let mut finder = AwaitFinder::default();
+        for stmt in stmts.iter() {
+            if !matches!(stmt, Stmt::Item(_)) {
+                finder.visit_stmt(stmt);
+            }
+            if let Stmt::Item(item) = stmt {
+                match item_names(item) {
+                    Some(names) if names.iter().all(|name| !reserved.contains(name)) => {
+                        body.items.push(offset(range_of(item)?));
+                    }
+                    // Items conflicting with others at the module level
+                    // may only shadow them inside the function,
+                    // and other items may depend on them.
+                    _ => hoistable = false,
+                }
+            }
+        }
+        if !hoistable {
+            body.items.clear();
+        }
+        body.expr = match stmts.last() {
+            Some(stmt @ Stmt::Expr(expr, None)) if is_printable(expr) => {
+                Some(offset(range_of(stmt)?))
+            }
+            Some(stmt @ Stmt::Macro(stmt_macro))
+                if stmt_macro.semi_token.is_none()
+                    && macro_value(&stmt_macro.mac) == Value::Other =>
+            {
+                Some(offset(range_of(stmt)?))
+            }
+            _ => None,
+        };
+        body.expr_is_async = matches!(stmts.last(), Some(Stmt::Expr(Expr::Async(_), None)));
+        body.is_async = finder.found || body.expr_is_async;
+        Some(body)
+    }
+
+    /// Find the `main` function at the top level of the code,
+    /// returning the position right after the opening brace of its body.
+    pub fn find_main(&self) -> Option<usize> {
+        let (items, offset): (Vec<_>, _) = if let Some(file) = self.file() {
+            (file.items.iter().collect(), 0)
+        } else if let Some(stmts) = self.body() {
+            let items = stmts.iter().filter_map(|stmt| match stmt {
+                Stmt::Item(item) => Some(item),
+                _ => None,
+            });
+            (items.collect(), self.body_start)
+        } else {
+            // Look for it in the text if the code fails to parse.
+            static RE_MAIN: Lazy<Regex> = Lazy::new(|| Regex::new(r"\bfn\s+main\s*\(").unwrap());
+            let pos = RE_MAIN.find(self.code)?.end();
+            return self.code[pos..].find('{').map(|brace| pos + brace + 1);
+        };
+        let main = items.into_iter().find_map(|item| match item {
+            Item::Fn(item_fn) if item_fn.sig.ident == "main" => Some(item_fn),
+            _ => None,
+        })?;
+        Some(main.block.brace_token.span.open().byte_range().end + offset)
+    }
+
+    /// Split the code into types separated by commas.
+    ///
+    /// Returns `None` if the code is not a list of types.
+    pub fn split_types(&self) -> Option<Vec<&'a str>> {
+        if self.is_too_long() {
+            return None;
+        }
+        let parser = Punctuated::<Type, Token![,]>::parse_terminated;
+        let types = parser.parse_str(self.code).ok()?;
+        let types = types
+            .iter()
+            .map(|ty| Some(&self.code[range_of(ty)?]))
+            .collect::<Option<Vec<_>>>()?;
+        (!types.is_empty()).then_some(types)
+    }
+
+    /// Find lines continuing multi-line literals in the code, counting from zero.
+    ///
+    /// Leading whitespace of these lines is part of the literals.
+    pub fn literal_continuation_lines(&self) -> HashSet<usize> {
+        if self.is_too_long() {
+            return HashSet::new();
+        }
+        let tokens = match self.code.parse::<TokenStream>() {
+            Ok(tokens) => tokens,
+            Err(_) => return HashSet::new(),
+        };
+        let mut lines = HashSet::new();
+        // Groups are walked with a stack, so that deep nesting is fine.
+        let mut stack = vec![tokens.into_iter()];
+        while let Some(iter) = stack.last_mut() {
+            match iter.next() {
+                Some(TokenTree::Group(group)) => stack.push(group.stream().into_iter()),
+                Some(TokenTree::Literal(literal)) => {
+                    let span = literal.span();
+                    // Lines of spans count from one.
+                    lines.extend(span.start().line..span.end().line);
+                }
+                Some(_) => {}
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+        lines
+    }
+
+    /// Collect names defined at the top level of the code.
+    pub fn defined_names(&self) -> HashSet<String> {
+        self.file().map_or_else(HashSet::new, |file| {
+            file.items.iter().filter_map(item_names).flatten().collect()
+        })
+    }
+
+    /// Find functions at the top level of the code which aren't public,
+    /// returning the positions their signatures start at.
+    pub fn private_fns(&self) -> Vec<usize> {
+        let items = self.file().map_or(&[][..], |file| &file.items);
+        let fns = items.iter().filter_map(|item| match item {
+            Item::Fn(item_fn) if matches!(item_fn.vis, Visibility::Inherited) => {
+                Some(range_of(&item_fn.sig)?.start)
+            }
+            _ => None,
+        });
+        fns.collect()
+    }
+}
+
+/// Visitor finding `.await` outside of any async context in the code.
+#[derive(Default)]
+struct AwaitFinder {
+    found: bool,
+}
+
+impl<'ast> Visit<'ast> for AwaitFinder {
+    fn visit_expr_await(&mut self, node: &'ast ExprAwait) {
+        self.found = true;
+        visit::visit_expr_await(self, node);
+    }
+
+    // Awaiting is fine inside async blocks, and invalid inside other closures and items.
+    fn visit_expr_async(&mut self, _: &'ast ExprAsync) {}
+    fn visit_expr_closure(&mut self, _: &'ast ExprClosure) {}
+    fn visit_item(&mut self, _: &'ast Item) {}
+}
+
+/// Get the names the item defines, or `None` if it cannot be put at the module level.
+fn item_names(item: &Item) -> Option<Vec<String>> {
+    let ident = match item {
+        Item::Const(item) => &item.ident,
+        Item::Enum(item) => &item.ident,
+        Item::Fn(item) => &item.sig.ident,
+        Item::Macro(item) => item.ident.as_ref()?,
+        Item::Mod(item) => &item.ident,
+        Item::Static(item) => &item.ident,
+        Item::Struct(item) => &item.ident,
+        Item::Trait(item) => &item.ident,
+        Item::TraitAlias(item) => &item.ident,
+        Item::Type(item) => &item.ident,
+        Item::Union(item) => &item.ident,
+        Item::Impl(_) | Item::ForeignMod(_) => return Some(vec![]),
+        Item::Use(item) => {
+            let mut names = vec![];
+            collect_use_names(&item.tree, None, &mut names);
+            return Some(names);
+        }
+        _ => return None,
+    };
+    Some(vec![ident.to_string()])
+}
+
+fn collect_use_names(tree: &UseTree, parent: Option<String>, names: &mut Vec<String>) {
+    match tree {
+        UseTree::Path(path) => collect_use_names(&path.tree, Some(path.ident.to_string()), names),
+        UseTree::Name(name) if name.ident == "self" => names.extend(parent),
+        UseTree::Name(name) => names.push(name.ident.to_string()),
+        UseTree::Rename(rename) => names.push(rename.rename.to_string()),
+        UseTree::Glob(_) => {}
+        UseTree::Group(group) => group
+            .items
+            .iter()
+            .for_each(|tree| collect_use_names(tree, parent.clone(), names)),
+    }
+}
+
+/// Check whether the value of the trailing expression is worth printing.
+fn is_printable(expr: &Expr) -> bool {
+    value_of(expr) == Value::Other
+}
+
+/// Value of an expression as far as the syntax tells.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Value {
+    /// The value is `()`.
+    Unit,
+    /// The expression never evaluates to a value.
+    Never,
+    /// Anything else.
+    Other,
+}
+
+fn value_of(expr: &Expr) -> Value {
+    match expr {
+        Expr::Assign(_) | Expr::ForLoop(_) | Expr::While(_) => Value::Unit,
+        Expr::Binary(binary) if is_assign_op(&binary.op) => Value::Unit,
+        Expr::Tuple(tuple) if tuple.elems.is_empty() => Value::Unit,
+        Expr::Return(_) | Expr::Break(_) | Expr::Continue(_) => Value::Never,
+        Expr::Macro(expr_macro) => macro_value(&expr_macro.mac),
+        Expr::Paren(paren) => value_of(&paren.expr),
+        // These have the value of one of their branches.
+        Expr::Block(block) => block_value(&block.block),
+        Expr::Unsafe(block) => block_value(&block.block),
+        Expr::If(expr_if) => match &expr_if.else_branch {
+            Some((_, else_branch)) => {
+                branches_value([block_value(&expr_if.then_branch), value_of(else_branch)])
+            }
+            None => Value::Unit,
+        },
+        Expr::Match(expr_match) => {
+            branches_value(expr_match.arms.iter().map(|arm| value_of(&arm.body)))
+        }
+        _ => Value::Other,
+    }
+}
+
+fn block_value(block: &Block) -> Value {
+    let value = match block.stmts.last() {
+        Some(Stmt::Expr(expr, semi)) => (value_of(expr), semi.is_some()),
+        Some(Stmt::Macro(stmt_macro)) => (
+            macro_value(&stmt_macro.mac),
+            stmt_macro.semi_token.is_some(),
+        ),
+        _ => return Value::Unit,
+    };
+    match value {
+        // A statement ending with a semicolon makes the block `()`,
+        // unless it never completes.
+        (Value::Never, _) => Value::Never,
+        (_, true) => Value::Unit,
+        (value, false) => value,
+    }
+}
+
+/// Get the value of branches, which have the same type
+/// except for those which never complete.
+fn branches_value(values: impl IntoIterator<Item = Value>) -> Value {
+    let values: Vec<_> = values.into_iter().collect();
+    if values.contains(&Value::Unit) {
+        Value::Unit
+    } else if values.contains(&Value::Other) {
+        Value::Other
+    } else {
+        Value::Never
+    }
+}
+
+fn is_assign_op(op: &BinOp) -> bool {
+    matches!(
+        op,
+        BinOp::AddAssign(_)
+            | BinOp::SubAssign(_)
+            | BinOp::MulAssign(_)
+            | BinOp::DivAssign(_)
+            | BinOp::RemAssign(_)
+            | BinOp::BitXorAssign(_)
+            | BinOp::BitAndAssign(_)
+            | BinOp::BitOrAssign(_)
+            | BinOp::ShlAssign(_)
+            | BinOp::ShrAssign(_)
+    )
+}
+
+/// Get the value of common macros.
+fn macro_value(mac: &Macro) -> Value {
+    const UNIT_MACROS: &[&str] = &[
+        "print",
+        "println",
+        "eprint",
+        "eprintln",
+        "assert",
+        "assert_eq",
+        "assert_ne",
+        "debug_assert",
+        "debug_assert_eq",
+        "debug_assert_ne",
+    ];
+    const NEVER_MACROS: &[&str] = &["panic", "todo", "unimplemented", "unreachable"];
+    let name = match mac.path.segments.last() {
+        Some(segment) => segment.ident.to_string(),
+        None => return Value::Other,
+    };
+    if UNIT_MACROS.contains(&name.as_str()) {
+        Value::Unit
+    } else if NEVER_MACROS.contains(&name.as_str()) {
+        Value::Never
+    } else {
+        Value::Other
+    }
+}
+
+/// Get the range of the code covered by the tokens.
+fn range_of(tokens: &impl ToTokens) -> Option<Range<usize>> {
+    let mut spans = tokens.to_token_stream().into_iter().map(|tt| tt.span());
+    let first = spans.next()?;
+    let last = spans.last().unwrap_or(first);
+    Some(first.byte_range().start..last.byte_range().end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    fn with_parsed<T: Send + 'static>(
+        code: &str,
+        f: impl FnOnce(&Parsed<'_>) -> T + Send + 'static,
+    ) -> T {
+        block_on(parse(code.to_string(), 0, f))
+    }
+
+    fn analyze_body(code: &str, reserved: HashSet<String>) -> Option<Body> {
+        with_parsed(code, move |parsed| parsed.analyze_body(&reserved))
+    }
+
+    fn find_main(code: &str) -> Option<usize> {
+        with_parsed(code, |parsed| parsed.find_main())
+    }
+
+    fn split_types(text: &str) -> Option<Vec<String>> {
+        with_parsed(text, |parsed| {
+            let types = parsed.split_types()?;
+            Some(types.into_iter().map(str::to_string).collect())
+        })
+    }
+
+    fn analyze(code: &str) -> Option<(Vec<&str>, Option<&str>)> {
+        let reserved = ["HashMap", "Cell"].iter().map(|s| s.to_string()).collect();
+        let body = analyze_body(code, reserved)?;
+        let items = body.items.into_iter().map(|r| &code[r]).collect();
+        Some((items, body.expr.map(|r| &code[r])))
+    }
+
+    #[test]
+    fn test_analyze_body() {
+        assert_eq!(analyze("1 + 1"), Some((vec![], Some("1 + 1"))));
+        assert_eq!(
+            analyze("#[derive(Debug)]\nstruct A;\n// A\nlet a = A;\na"),
+            Some((vec!["#[derive(Debug)]\nstruct A;"], Some("a"))),
+        );
+        assert_eq!(
+            analyze("fn f() -> &'static str { \"println!\" }\nf() // done"),
+            Some((vec!["fn f() -> &'static str { \"println!\" }"], Some("f()"))),
+        );
+        assert_eq!(
+            analyze("let x = 1; println!(\"{x}\")"),
+            Some((vec![], None)),
+        );
+        assert_eq!(
+            analyze("let x = 1; println!(\"{x}\"); vec![x]"),
+            Some((vec![], Some("vec![x]"))),
+        );
+        assert_eq!(analyze("for i in 0..2 { dbg!(i); }"), Some((vec![], None)),);
+        assert_eq!(
+            analyze("let s = \"啊\"; impl A {} s.len()"),
+            Some((vec!["impl A {}"], Some("s.len()"))),
+        );
+        assert_eq!(
+            analyze("use std::fmt::Write;\nuse std::{cell, rc::Rc as R};"),
+            Some((
+                vec!["use std::fmt::Write;", "use std::{cell, rc::Rc as R};"],
+                None
+            )),
+        );
+        // Nothing is hoisted if any item conflicts with reserved names.
+        assert_eq!(
+            analyze("struct Cell; fn f() {} 1"),
+            Some((vec![], Some("1"))),
+        );
+        assert_eq!(
+            analyze("use std::collections::{self, HashMap}; 1"),
+            Some((vec![], Some("1"))),
+        );
+        assert_eq!(analyze("let a = ;"), None);
+    }
+
+    #[test]
+    fn test_analyze_compound_expr() {
+        let expr = |code| analyze(code).unwrap().1;
+        assert_eq!(
+            expr("if x { println!(\"a\") } else { println!(\"b\") }"),
+            None
+        );
+        assert_eq!(
+            expr("if x { 1 } else if y { 2 } else { 3 }"),
+            Some("if x { 1 } else if y { 2 } else { 3 }"),
+        );
+        assert_eq!(
+            expr("match x { 0 => f(), _ => unreachable!() }"),
+            Some("match x { 0 => f(), _ => unreachable!() }")
+        );
+        assert_eq!(expr("match x { 0 => {} _ => return }"), None);
+        assert_eq!(expr("{ let a = 1; a }"), Some("{ let a = 1; a }"));
+        assert_eq!(expr("unsafe { f(); }"), None);
+        assert_eq!(expr("{ panic!() }"), None);
+        assert_eq!(expr("x += 1"), None);
+        assert_eq!(expr("()"), None);
+        assert_eq!(expr("(1)"), Some("(1)"));
+    }
+
+    #[test]
+    fn test_analyze_after_headers() {
+        let code = "#![feature(test)]\nextern crate a;\nstruct A;\nA";
+        let body_start = code.find("struct").unwrap();
+        let (body, main) = block_on(parse(code.to_string(), body_start, |parsed| {
+            (parsed.analyze_body(&HashSet::new()), parsed.find_main())
+        }));
+        let body = body.unwrap();
+        assert_eq!(
+            body.items.into_iter().map(|r| &code[r]).collect::<Vec<_>>(),
+            ["struct A;"]
+        );
+        assert_eq!(body.expr.map(|r| &code[r]), Some("A"));
+        assert_eq!(main, None);
+        let code = "#![feature(test)]\nlet a = 1;\nfn main() {}";
+        let body_start = code.find("let").unwrap();
+        let main = block_on(parse(code.to_string(), body_start, |parsed| {
+            parsed.find_main()
+        }));
+        assert_eq!(main.map(|pos| &code[..pos]), Some(&code[..code.len() - 1]));
+    }
+
+    #[test]
+    fn test_deeply_nested() {
+        // These would overflow the stack of the current thread.
+        let nested = |open: &str, inner: &str, close: &str| {
+            let depth = (MAX_CODE_LEN - inner.len()) / (open.len() + close.len());
+            format!("{}{inner}{}", open.repeat(depth), close.repeat(depth))
+        };
+        let code = nested("(", "1", ")");
+        assert!(analyze_body(&code, HashSet::new()).unwrap().expr.is_some());
+        assert_eq!(find_main(&code), None);
+        let code = nested("{", "", "}");
+        assert!(analyze_body(&code, HashSet::new()).is_some());
+        let code = nested("!", "x", "");
+        assert!(analyze_body(&code, HashSet::new()).is_some());
+        let code = nested("if x { 1 } else ", "{ 1 }", "");
+        assert!(analyze_body(&code, HashSet::new()).is_some());
+        let code = nested("[", "u8", "]");
+        assert_eq!(split_types(&code).map(|types| types.len()), Some(1));
+        let code = nested("Option<", "u8", ">");
+        assert_eq!(split_types(&code).map(|types| types.len()), Some(1));
+        let code = nested("&", "u8", "");
+        assert_eq!(split_types(&code).map(|types| types.len()), Some(1));
+        // Longer code is not parsed at all.
+        let code = format!("{} fn main() {{}}", "(".repeat(MAX_CODE_LEN));
+        assert_eq!(analyze_body(&code, HashSet::new()), None);
+        assert_eq!(find_main(&code), Some(code.len() - 1));
+    }
+
+    #[test]
+    fn test_parser_panic() {
+        let result = panic::catch_unwind(|| with_parsed("1", |_| panic!("failed")));
+        assert!(result.is_err());
+        // The parser keeps working for later code.
+        assert!(analyze_body("1", HashSet::new()).is_some());
+    }
+
+    #[test]
+    fn test_analyze_async() {
+        let analyze = |code| {
+            let body = analyze_body(code, HashSet::new()).unwrap();
+            (body.is_async, body.expr_is_async)
+        };
+        assert_eq!(analyze("1 + 1"), (false, false));
+        assert_eq!(analyze("tokio::time::sleep(d).await; 1"), (true, false));
+        assert_eq!(
+            analyze("let a = f(async { g().await }.await);"),
+            (true, false)
+        );
+        assert_eq!(analyze("async { 1 }"), (true, true));
+        assert_eq!(analyze("let f = async { g().await }; 1"), (false, false));
+        assert_eq!(analyze("let c = async || g().await; 1"), (false, false));
+        assert_eq!(analyze("async fn f() { g().await } 1"), (false, false));
+    }
+
+    #[test]
+    fn test_find_main() {
+        let find = |code| find_main(code).map(|pos| &code[..pos]);
+        assert_eq!(find("fn main() {}"), Some("fn main() {"));
+        assert_eq!(
+            find("#![feature(test)]\nfn main () {}"),
+            Some("#![feature(test)]\nfn main () {")
+        );
+        assert_eq!(
+            find("async fn main() -> Result<(), ()> { Ok(()) }"),
+            Some("async fn main() -> Result<(), ()> {"),
+        );
+        assert_eq!(
+            find("use a::b; let x = 1; fn main() {}"),
+            Some("use a::b; let x = 1; fn main() {"),
+        );
+        assert_eq!(find("// fn main() {}\n1"), None);
+        assert_eq!(find("mod a { fn main() {} }"), None);
+        assert_eq!(find("fn main() { let = }"), Some("fn main() {"));
+        assert_eq!(find("fn f() {}"), None);
+    }
+
+    #[test]
+    fn test_split_types() {
+        assert_eq!(
+            split_types("Option<Box<[u8]>>, HashMap<u8, fn(u8) -> u8>,").unwrap(),
+            ["Option<Box<[u8]>>", "HashMap<u8, fn(u8) -> u8>"],
+        );
+        assert_eq!(split_types(" &'static str ").unwrap(), ["&'static str"]);
+        assert_eq!(split_types(""), None);
+        assert_eq!(split_types("1 + 1"), None);
+    }
+
+    #[test]
+    fn test_literal_continuation_lines() {
+        let lines = |code| with_parsed(code, |parsed| parsed.literal_continuation_lines());
+        let code = "let a = \"x\n    y\n\";\nlet b = r#\"\n\"#; // \"\n'a';\n";
+        let mut lines_in_code = lines(code).into_iter().collect::<Vec<_>>();
+        lines_in_code.sort();
+        assert_eq!(lines_in_code, [1, 2, 4]);
+        assert!(lines("\"unclosed\n").is_empty());
+    }
+
+    #[test]
+    fn test_private_fns() {
+        let private_fns = |code| with_parsed(code, |parsed| parsed.private_fns());
+        let code = "#[inline]\nfn f() {}\npub fn g() {}\nconst unsafe fn h() {}\nstruct A;";
+        let fns: Vec<_> = private_fns(code)
+            .into_iter()
+            .map(|pos| &code[pos..])
+            .collect();
+        assert_eq!(
+            fns,
+            [
+                "fn f() {}\npub fn g() {}\nconst unsafe fn h() {}\nstruct A;",
+                "const unsafe fn h() {}\nstruct A;"
+            ],
+        );
+        assert!(private_fns("fn f() {").is_empty());
+    }
+
+    #[test]
+    fn test_defined_names() {
+        let code = "use std::cell::{self, Cell as C};\nfn f() {}\nstruct A;";
+        let names = with_parsed(code, |parsed| parsed.defined_names());
+        let mut names: Vec<_> = names.into_iter().collect();
+        names.sort();
+        assert_eq!(names, ["A", "C", "cell", "f"]);
+    }
+}
@@ -1,9 +1,13 @@
 #![recursion_limit = "128"]
 
+mod admin;
+mod blocklist;
 mod bot;
 mod bot_runner;
 mod cratesio;
+mod error_report;
 mod eval;
+mod maintenance;
 mod rustdoc;
 mod shutdown;
 #[cfg(unix)]
@@ -12,31 +16,24 @@ mod task_tracker;
 mod upgrade;
 mod utils;
 
-use crate::bot::{Bot, Error};
-use crate::bot_runner::BotRunner;
+use crate::bot_runner::{BotRunner, BotStatus};
 use crate::cratesio::CratesioBot;
+use crate::error_report::ErrorReporter;
 use crate::eval::EvalBot;
 use crate::rustdoc::RustdocBot;
 use crate::shutdown::Shutdown;
 use futures::channel::oneshot::Receiver;
-use futures::future::{self, TryFutureExt as _};
-use itertools::Itertools;
+use futures::future;
+use htmlescape::encode_minimal;
 use log::{error, info};
 use once_cell::sync::Lazy;
 use reqwest::Client;
-use std::env;
 use std::fmt::Write as FmtWrite;
-use std::future::Future;
 use std::io::Write as IOWrite;
-use telegram_types::bot::types::{ChatId, UserId};
+use std::time::Instant;
 use tokio::runtime::Runtime;
 
-static ADMIN_ID: Lazy<UserId> = Lazy::new(|| {
-    env::var("BOT_ADMIN_ID")
-        .ok()
-        .and_then(|s| str::parse(&s).map(UserId).ok())
-        .expect("BOT_ADMIN_ID must be a valid user id")
-});
+static START_TIME: Lazy<Instant> = Lazy::new(Instant::now);
 static ABOUT_MESSAGE: Lazy<String> = Lazy::new(|| {
     format!(
         "{} {}\n{}",
@@ -50,23 +47,35 @@ fn main() {
     // We don't care if we fail to load .env file.
     let _ = dotenv::from_path(std::env::current_dir().unwrap().join(".env"));
     init_logger();
+    Lazy::force(&START_TIME);
+    admin::init();
+    blocklist::init();
+    maintenance::init();
 
+    // Subsystems which failed to initialize, and the reasons.
+    let mut degraded = Vec::new();
     let shutdown = Shutdown::create();
     #[cfg(unix)]
-    signal::init(shutdown.clone());
-    upgrade::init(shutdown.clone());
-    rustdoc::init();
+    if let Err(e) = signal::init(shutdown.clone()) {
+        error!("failed to init signal handler: {:?}", e);
+        degraded.push(("signal handler", e.to_string()));
+    }
+    if let Err(e) = upgrade::init(shutdown.clone()) {
+        error!("failed to init upgrade watcher: {:?}", e);
+        degraded.push(("upgrade watcher", e.to_string()));
+    }
 
     info!("Running as `{}`", env!("USER_AGENT"));
 
     let runtime = Runtime::new().unwrap();
     let (spawner, waiter) = task_tracker::create(&runtime);
     let client = build_client();
+    let reporter = ErrorReporter::new(spawner.clone(), shutdown.clone());
     let bot_runner = BotRunner {
         client: &client,
         spawner: &spawner,
         shutdown: &shutdown,
-        report_error: report_error_to_admin,
+        reporter: &reporter,
     };
 
     // Kick off eval bot.
@@ -74,7 +83,7 @@ fn main() {
     let eval_receiver = bot_runner.run(
         "eval",
         "EVAL_TELEGRAM_TOKEN",
-        move |bot| EvalBot::new(client_clone, bot),
+        move |bot| Ok(EvalBot::new(client_clone, bot)),
         EvalBot::handle_update,
     );
 
@@ -83,7 +92,7 @@ fn main() {
     let cratesio_receiver = bot_runner.run(
         "cratesio",
         "CRATESIO_TELEGRAM_TOKEN",
-        move |bot| CratesioBot::new(client_clone, bot),
+        move |bot| Ok(CratesioBot::new(client_clone, bot)),
         CratesioBot::handle_update,
     );
 
@@ -96,37 +105,62 @@ fn main() {
     );
 
     async fn bind_name(
-        receiver: Receiver<Result<Option<Bot>, ()>>,
+        receiver: Receiver<BotStatus>,
         name: &'static str,
-    ) -> Result<Option<(&'static str, Bot)>, ()> {
-        let b = receiver.await.map_err(|_| ())?;
-        Ok(b?.map(|b| (name, b)))
+    ) -> (&'static str, BotStatus) {
+        let status = receiver
+            .await
+            .unwrap_or_else(|_| BotStatus::Failed("bot runner dropped".to_string()));
+        (name, status)
     }
 
     let bot = runtime.block_on(async {
-        let bots = future::try_join_all(vec![
+        let bots = future::join_all(vec![
             bind_name(eval_receiver, "eval"),
             bind_name(cratesio_receiver, "cratesio"),
             bind_name(rustdoc_receiver, "rustdoc"),
         ])
-        .await
-        .unwrap();
-        let bots = bots.into_iter().flatten().collect_vec();
+        .await;
         let mut start_msg = format!("Start version: {}", env!("VERSION"));
-        for (name, bot) in bots.iter() {
-            write!(&mut start_msg, "\nbot {} @{}", name, bot.username).unwrap();
+        let mut first_bot = None;
+        for (name, status) in bots {
+            match status {
+                BotStatus::Disabled => {}
+                BotStatus::Failed(reason) => {
+                    let reason = encode_minimal(&reason);
+                    write!(&mut start_msg, "\nbot {name} failed: {reason}").unwrap();
+                }
+                BotStatus::Running(bot) => {
+                    write!(&mut start_msg, "\nbot {} @{}", name, bot.username).unwrap();
+                    first_bot.get_or_insert(bot);
+                }
+            }
+        }
+        for (name, reason) in degraded {
+            let reason = encode_minimal(&reason);
+            write!(&mut start_msg, "\n{name} disabled: {reason}").unwrap();
+        }
+        match &first_bot {
+            Some(bot) => {
+                let _ = admin::notify(bot, start_msg).await;
+            }
+            // There is no bot to notify the admins via.
+            None => error!("no bot is running\n{}", start_msg),
         }
-        let (_, first_bot) = bots.into_iter().next().expect("no bot configured?");
-        send_message_to_admin(&first_bot, start_msg).await.unwrap();
         first_bot
     });
+    let bot = match bot {
+        Some(bot) => bot,
+        None => {
+            shutdown.shutdown();
+            std::process::exit(1);
+        }
+    };
 
     runtime.block_on(async move {
         waiter.wait().await;
         // Send the final message.
-        send_message_to_admin(&bot, "bye".to_string())
-            .await
-            .unwrap();
+        let _ = admin::notify(&bot, "bye".to_string()).await;
     });
 }
 
@@ -167,24 +201,3 @@ fn build_client() -> Client {
     headers.insert(USER_AGENT, env!("USER_AGENT").parse().unwrap());
     Client::builder().default_headers(headers).build().unwrap()
 }
-
-fn report_error_to_admin(bot: &Bot, error: &Error) {
-    use htmlescape::encode_minimal;
-    let message = match error {
-        Error::Parse(bot::ParseError { data, error }) => format!(
-            "parse failed: {:?}\n<pre>{}</pre>",
-            encode_minimal(&format!("{error:?}")),
-            encode_minimal(&String::from_utf8_lossy(data)),
-        ),
-        _ => encode_minimal(&format!("{error:?}")),
-    };
-    tokio::spawn(send_message_to_admin(bot, message));
-}
-
-fn send_message_to_admin(bot: &Bot, msg: String) -> impl Future<Output = Result<(), ()>> {
-    let chat_id = ChatId(ADMIN_ID.0);
-    bot.send_message(chat_id, msg)
-        .execute()
-        .map_ok(|_| ())
-        .map_err(|e| error!("failed to send message to admin: {:?}", e))
-}
@@ -0,0 +1,119 @@
+use htmlescape::encode_minimal;
+use log::error;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io;
+use telegram_types::bot::inline_mode::{
+    InlineQueryResult, InlineQueryResultArticle, InputMessageContent, InputTextMessageContent,
+    ResultId,
+};
+use telegram_types::bot::types::ParseMode;
+
+const MAINTENANCE_FILE: &str = "maintenance.json";
+const DEFAULT_NOTICE: &str = "The bot is under maintenance, please try again later.";
+
+/// Time in seconds inline query results can be cached by Telegram in maintenance mode.
+///
+/// Caching is disabled so that the results stop showing up as soon as
+/// the maintenance mode is turned off.
+const INLINE_CACHE_TIME: i32 = 0;
+
+/// Notice of the maintenance, `None` if not in maintenance mode.
+static NOTICE: Lazy<RwLock<Option<String>>> = Lazy::new(|| RwLock::new(load()));
+
+#[derive(Deserialize, Serialize)]
+struct Maintenance {
+    notice: String,
+}
+
+pub fn init() {
+    Lazy::force(&NOTICE);
+}
+
+/// Get the maintenance notice if the bots are in maintenance mode.
+pub fn notice() -> Option<String> {
+    NOTICE.read().clone()
+}
+
+/// Get the cache time for answering inline queries, `None` for the default.
+///
+/// It should be taken right before answering, so that results which took
+/// a while to generate aren't cached after the maintenance mode is turned on.
+pub fn inline_cache_time() -> Option<i32> {
+    NOTICE.read().as_ref().map(|_| INLINE_CACHE_TIME)
+}
+
+/// Turn on maintenance mode with the given notice, or the default one if empty.
+pub fn start(notice: &str) {
+    let notice = match notice.trim() {
+        "" => DEFAULT_NOTICE.to_string(),
+        notice => notice.to_string(),
+    };
+    let mut guard = NOTICE.write();
+    match File::create(MAINTENANCE_FILE) {
+        Ok(file) => {
+            let maintenance = Maintenance {
+                notice: notice.clone(),
+            };
+            if let Err(e) = serde_json::to_writer(file, &maintenance) {
+                error!("failed to serialize maintenance: {:?}", e);
+            }
+        }
+        Err(e) => error!("failed to create maintenance file: {:?}", e),
+    }
+    *guard = Some(notice);
+}
+
+/// Turn off maintenance mode.
+pub fn stop() {
+    let mut guard = NOTICE.write();
+    if let Err(e) = fs::remove_file(MAINTENANCE_FILE) {
+        if e.kind() != io::ErrorKind::NotFound {
+            error!("failed to remove maintenance file: {:?}", e);
+        }
+    }
+    *guard = None;
+}
+
+/// Generate the message for replying to users with the given notice.
+pub fn message(notice: &str) -> String {
+    format!("<b>Maintenance</b>: {}", encode_minimal(notice))
+}
+
+/// Generate the only result for inline queries with the given notice.
+pub fn inline_query_result(notice: &str) -> InlineQueryResult<'static> {
+    InlineQueryResult::Article(InlineQueryResultArticle {
+        id: ResultId("maintenance".to_string()),
+        title: "Under maintenance".into(),
+        input_message_content: InputMessageContent::Text(InputTextMessageContent {
+            message_text: message(notice).into(),
+            parse_mode: Some(ParseMode::HTML),
+            disable_web_page_preview: Some(true),
+        }),
+        reply_markup: None,
+        url: None,
+        hide_url: None,
+        description: Some(notice.to_string().into()),
+        thumb_url: None,
+        thumb_width: None,
+        thumb_height: None,
+    })
+}
+
+fn load() -> Option<String> {
+    match File::open(MAINTENANCE_FILE) {
+        Ok(file) => match serde_json::from_reader::<_, Maintenance>(file) {
+            Ok(maintenance) => return Some(maintenance.notice),
+            Err(e) => error!("failed to parse maintenance file: {:?}", e),
+        },
+        Err(e) => {
+            // It's fine that the file doesn't exist.
+            if e.kind() != io::ErrorKind::NotFound {
+                error!("failed to read maintenance file: {:?}", e);
+            }
+        }
+    }
+    None
+}
@@ -1,5 +1,6 @@
 use self::search::ItemType;
 use crate::bot::Bot;
+use crate::maintenance;
 use crate::utils::encode_with_code;
 use itertools::Itertools;
 use log::{info, warn};
@@ -14,16 +15,15 @@ use telegram_types::bot::types::{ParseMode, UpdateContent, UpdateId};
 
 mod search;
 
-pub use self::search::init;
-
 pub struct RustdocBot {
     bot: Bot,
 }
 
 impl RustdocBot {
-    pub fn new(bot: Bot) -> Self {
+    pub fn new(bot: Bot) -> Result<Self, String> {
+        search::init()?;
         info!("RustdocBot authorized as @{}", bot.username);
-        RustdocBot { bot }
+        Ok(RustdocBot { bot })
     }
 
     pub async fn handle_update(self: Arc<Self>, _: UpdateId, content: UpdateContent) {
@@ -31,14 +31,18 @@ impl RustdocBot {
             UpdateContent::InlineQuery(query) => query,
             _ => return,
         };
-        let result = search::query(&query.query)
-            .into_iter()
-            .take(50)
-            .map(doc_item_to_result)
-            .collect_vec();
+        let notice = maintenance::notice();
+        let result = match notice {
+            Some(notice) => vec![maintenance::inline_query_result(&notice)],
+            None => search::query(&query.query)
+                .into_iter()
+                .take(50)
+                .map(doc_item_to_result)
+                .collect_vec(),
+        };
         let result = self
             .bot
-            .answer_inline_query(query.id, &result)
+            .answer_inline_query(query.id, &result, maintenance::inline_cache_time())
             .execute()
             .await;
         if let Err(e) = result {
@@ -60,7 +64,7 @@ fn doc_item_to_result(item: &DocItem) -> InlineQueryResult<'static> {
             let is_parent_keyword_or_primitive = item
                 .parent
                 .as_ref()
-                .map_or(false, |p| ItemType::from(p).is_keyword_or_primitive());
+                .is_some_and(|p| ItemType::from(p).is_keyword_or_primitive());
             if !is_parent_keyword_or_primitive {
                 result.push_str(item.path.as_ref());
                 result.push_str("::");
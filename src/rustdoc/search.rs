@@ -1,12 +1,17 @@
 use fst_subseq_ascii_caseless::SubseqAsciiCaseless;
-use once_cell::sync::Lazy;
+use once_cell::sync::OnceCell;
 use rustdoc_seeker::{DocItem, RustDoc, RustDocSeeker, TypeItem};
 use std::fs;
 use std::ops::Deref;
 
-static SEEKER: Lazy<RustDocSeeker> = Lazy::new(|| {
-    let data = fs::read_to_string("search-index.js").expect("cannot find search-index.js");
-    let doc: RustDoc = data.parse().expect("cannot parse search-index.js");
+static SEEKER: OnceCell<RustDocSeeker> = OnceCell::new();
+
+fn load_seeker() -> Result<RustDocSeeker, String> {
+    let data = fs::read_to_string("search-index.js")
+        .map_err(|e| format!("cannot read search-index.js: {e}"))?;
+    let doc: RustDoc = data
+        .parse()
+        .map_err(|e| format!("cannot parse search-index.js: {e}"))?;
     if cfg!(debug_assertions) {
         const SPECIAL_CHARS: &[char] = &['<', '>', '"', '\'', '&'];
         for item in doc.iter() {
@@ -16,18 +21,18 @@ static SEEKER: Lazy<RustDocSeeker> = Lazy::new(|| {
                 || item
                     .parent
                     .as_ref()
-                    .map_or(false, |p| p.as_ref().contains(SPECIAL_CHARS))
+                    .is_some_and(|p| p.as_ref().contains(SPECIAL_CHARS))
                 || item.name.as_ref().contains(SPECIAL_CHARS)
             {
                 panic!("Found path with HTML special character: {item:?}");
             }
         }
     }
-    doc.build()
-});
+    Ok(doc.build())
+}
 
-pub fn init() {
-    Lazy::force(&SEEKER);
+pub fn init() -> Result<(), String> {
+    SEEKER.get_or_try_init(load_seeker).map(|_| ())
 }
 
 pub fn query(path: &str) -> Vec<&'static DocItem> {
@@ -41,7 +46,11 @@ pub fn query(path: &str) -> Vec<&'static DocItem> {
         None => return vec![],
     };
     let lowercase_name = name.to_ascii_lowercase();
-    let mut matched_items = SEEKER
+    let seeker = match SEEKER.get() {
+        Some(seeker) => seeker,
+        None => return vec![],
+    };
+    let mut matched_items = seeker
         .search(&SubseqAsciiCaseless::new(&lowercase_name))
         .filter(|item| matches_path(item, root, path))
         .collect::<Vec<_>>();
@@ -122,7 +131,7 @@ pub enum ItemType {
     Other,
 }
 
-impl<'a> From<&'a TypeItem> for ItemType {
+impl From<&TypeItem> for ItemType {
     fn from(item: &TypeItem) -> Self {
         match item {
             TypeItem::Keyword(_) => ItemType::Keyword,
@@ -2,21 +2,20 @@ use crate::shutdown::Shutdown;
 use log::info;
 use signal_hook::consts::{SIGINT, SIGTERM};
 use signal_hook::iterator::Signals;
+use std::io;
 use std::sync::Arc;
 use std::thread;
 
-pub fn init(shutdown: Arc<Shutdown>) {
-    let mut signals = Signals::new([SIGINT, SIGTERM]).expect("failed to init signal handler");
+pub fn init(shutdown: Arc<Shutdown>) -> io::Result<()> {
+    let mut signals = Signals::new([SIGINT, SIGTERM])?;
     thread::spawn(move || {
-        for signal in signals.forever() {
+        if let Some(signal) = signals.forever().next() {
             info!("signal: {}", signal);
             match signal {
-                SIGINT | SIGTERM => {
-                    shutdown.shutdown();
-                    break;
-                }
+                SIGINT | SIGTERM => shutdown.shutdown(),
                 _ => unreachable!(),
             }
         }
     });
+    Ok(())
 }
@@ -1,19 +1,38 @@
 use crate::shutdown::Shutdown;
 use log::{debug, info};
 use notify::{self, Event, EventKind, RecommendedWatcher, RecursiveMode, Result, Watcher};
+use std::fs::OpenOptions;
+use std::io::{self, Write as _};
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::Arc;
 use std::thread;
+use telegram_types::bot::types::UserId;
 
 const NOTIFY_FILE: &str = "upgrade";
 
-pub fn init(shutdown: Arc<Shutdown>) {
+/// Whether the notify file is being watched.
+static WATCHING: AtomicBool = AtomicBool::new(false);
+
+pub fn init(shutdown: Arc<Shutdown>) -> Result<()> {
     let (tx, rx) = mpsc::channel();
-    let watcher = init_watcher(tx).expect("failed to init upgrade watcher");
+    let watcher = init_watcher(tx)?;
+    WATCHING.store(true, Ordering::Relaxed);
     thread::spawn(move || {
         watch_notify_file(&watcher, &rx, &shutdown);
     });
+    Ok(())
+}
+
+/// Trigger an upgrade on request of the user by writing the notify file,
+/// the same way as the file gets written from outside.
+pub fn trigger(user: UserId) -> io::Result<()> {
+    if !WATCHING.load(Ordering::Relaxed) {
+        return Err(io::Error::other("upgrade watcher is disabled"));
+    }
+    let mut file = OpenOptions::new().write(true).open(NOTIFY_FILE)?;
+    writeln!(file, "requested by {}", user.0)
 }
 
 fn init_watcher(tx: Sender<Result<Event>>) -> Result<impl Watcher> {
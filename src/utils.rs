@@ -1,19 +1,9 @@
 use htmlescape::encode_minimal;
 use phf::phf_map;
 use std::borrow::Cow;
-use std::fmt;
-use telegram_types::bot::types::{ChatType, Message};
+use telegram_types::bot::types::{ChatType, Message, MessageEntity};
 use unicode_width::UnicodeWidthChar;
 
-#[derive(Clone, Copy, Debug)]
-pub enum Void {}
-
-impl fmt::Display for Void {
-    fn fmt(&self, _: &mut fmt::Formatter<'_>) -> fmt::Result {
-        Ok(())
-    }
-}
-
 pub fn truncate_output(output: &str, max_lines: usize, max_total_columns: usize) -> Cow<'_, str> {
     let mut line_count = 0;
     let mut column_count = 0;
@@ -42,6 +32,28 @@ pub fn is_message_from_private_chat(message: &Message) -> bool {
     matches!(message.chat.kind, ChatType::Private { .. })
 }
 
+/// Get the text covered by the entity.
+///
+/// Returns `None` if the range of the entity is invalid for the text.
+pub fn entity_text<'a>(text: &'a str, entity: &MessageEntity) -> Option<&'a str> {
+    // Offset and length of entities are in UTF-16 code units.
+    let start = usize::try_from(entity.offset).ok()?;
+    let end = start + usize::try_from(entity.length).ok()?;
+    let mut utf16_pos = 0;
+    let mut range = (None, None);
+    for (pos, c) in text.char_indices().chain(Some((text.len(), '\0'))) {
+        if utf16_pos == start {
+            range.0 = Some(pos);
+        }
+        if utf16_pos == end {
+            range.1 = Some(pos);
+            break;
+        }
+        utf16_pos += c.len_utf16();
+    }
+    text.get(range.0?..range.1?)
+}
+
 pub fn encode_with_code(output: &mut String, text: &str) {
     let mut is_code = false;
     for chunk in encode_minimal(text).split('`') {
@@ -71,7 +83,7 @@ static UNICODE_CHARS_MAP: phf::Map<char, &str> = phf_map! {
 /// [`UNICODE_CHARS_MAP`].
 ///
 /// Time complexity of this is `O(n)`.
-pub fn normalize_unicode_chars(input: &str) -> Cow<str> {
+pub fn normalize_unicode_chars(input: &str) -> Cow<'_, str> {
     // If the input is ASCII, there is no need to normalize.
     if input.is_ascii() {
         return input.into();
@@ -143,6 +155,24 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_entity_text() {
+        let entity = |offset, length| MessageEntity {
+            kind: telegram_types::bot::types::MessageEntityKind::Code,
+            offset,
+            length,
+            url: None,
+            user: None,
+        };
+        let text = "a 😀 `b` 啊";
+        assert_eq!(entity_text(text, &entity(0, 1)), Some("a"));
+        assert_eq!(entity_text(text, &entity(2, 2)), Some("😀"));
+        assert_eq!(entity_text(text, &entity(5, 3)), Some("`b`"));
+        assert_eq!(entity_text(text, &entity(9, 1)), Some("啊"));
+        assert_eq!(entity_text(text, &entity(3, 1)), None);
+        assert_eq!(entity_text(text, &entity(9, 2)), None);
+    }
+
     #[test]
     fn test_normalize_unicode_chars() {
         const TEST_MAP: &[(&str, &str)] = &[